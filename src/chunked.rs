@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use async_std::io::Read;
+use async_std::prelude::*;
+use crate::read_header_fields;
+
+pub async fn read_line<R>(reader: &mut R, limit: Option<usize>) -> Result<Vec<u8>, Error>
+    where
+    R: Read + Unpin,
+{
+    match read_line_or_eof(reader, limit).await? {
+        Some(line) => Ok(line),
+        None => Err(Error::new(ErrorKind::UnexpectedEof, "The stream ended while reading the HTTP line.")),
+    }
+}
+
+pub async fn read_line_or_eof<R>(reader: &mut R, limit: Option<usize>) -> Result<Option<Vec<u8>>, Error>
+    where
+    R: Read + Unpin,
+{
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+
+    loop {
+        if reader.read(&mut byte).await? == 0 {
+            return match line.is_empty() {
+                true => Ok(None),
+                false => Err(Error::new(ErrorKind::UnexpectedEof, "The stream ended while reading the HTTP line.")),
+            };
+        }
+        match byte[0] {
+            b'\n' if line.last() == Some(&b'\r') => {
+                line.pop();
+                return Ok(Some(line));
+            },
+            byte => line.push(byte),
+        }
+        if let Some(limit) = limit {
+            if byte[0] != b'\r' && line.len() > limit {
+                return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP line.", limit)));
+            }
+        }
+    }
+}
+
+pub async fn read_chunk_size<R>(reader: &mut R, limit: Option<usize>) -> Result<usize, Error>
+    where
+    R: Read + Unpin,
+{
+    let line = read_line(reader, limit).await?;
+    let line = String::from_utf8_lossy(&line);
+    let size = match line.find(';') {
+        Some(index) => &line[..index],
+        None => &line,
+    };
+    let size = size.trim();
+    if size.is_empty() || !size.bytes().all(|byte| byte.is_ascii_hexdigit()) { // from_str_radix would accept a sign
+        return Err(Error::new(ErrorKind::InvalidData, format!("The chunk size `{}` is invalid.", size)));
+    }
+    match usize::from_str_radix(size, 16) {
+        Ok(size) => Ok(size),
+        Err(_) => Err(Error::new(ErrorKind::InvalidData, format!("The chunk size `{}` is invalid.", size))),
+    }
+}
+
+pub fn incomplete_chunked(e: Error) -> Error {
+    match e.kind() {
+        ErrorKind::UnexpectedEof => Error::new(ErrorKind::UnexpectedEof, "The chunked body is incomplete, the stream ended before the last chunk."),
+        _ => e,
+    }
+}
+
+pub async fn read_chunked<R>(reader: &mut R, data: &mut Vec<u8>, trailers: &mut HashMap<String, String>, limits: (Option<usize>, Option<usize>), trailer_limits: (Option<usize>, usize)) -> Result<usize, Error>
+    where
+    R: Read + Unpin,
+{
+    read_chunks(reader, data, trailers, limits, trailer_limits).await.map_err(incomplete_chunked)
+}
+
+async fn read_chunks<R>(reader: &mut R, data: &mut Vec<u8>, trailers: &mut HashMap<String, String>, limits: (Option<usize>, Option<usize>), trailer_limits: (Option<usize>, usize)) -> Result<usize, Error>
+    where
+    R: Read + Unpin,
+{
+    let (chunkline_limit, body_limit) = limits;
+    let mut buffer = [0u8; 8192];
+    let mut total: usize = 0;
+
+    loop {
+        let size = read_chunk_size(reader, chunkline_limit).await?;
+        if size == 0 {
+            break;
+        }
+
+        total = match (total.checked_add(size), body_limit) {
+            (Some(total), Some(limit)) if total > limit => return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP body chunk data.", limit))),
+            (Some(total), _) => total,
+            (None, _) => return Err(Error::new(ErrorKind::InvalidData, "The chunked body size overflows.")),
+        };
+
+        let mut remaining = size;
+        while remaining > 0 { // grow with the data actually received, not the advertised size
+            let length = remaining.min(buffer.len());
+            reader.read_exact(&mut buffer[..length]).await?;
+            data.extend_from_slice(&buffer[..length]);
+            remaining -= length;
+        }
+
+        if !read_line(reader, Some(0)).await?.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "The chunk data is not terminated with CRLF."));
+        }
+    }
+
+    read_trailers(reader, trailers, trailer_limits).await?;
+    Ok(total)
+}
+
+pub async fn read_trailers<R>(reader: &mut R, trailers: &mut HashMap<String, String>, limits: (Option<usize>, usize)) -> Result<(), Error>
+    where
+    R: Read + Unpin,
+{
+    read_header_fields(reader, trailers, limits, false, "trailer").await
+}
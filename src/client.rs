@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+use async_std::io::Read;
+use async_std::task;
+use crate::{Connection, Request, Response, Middleware, Pool, Limiter, Status, Transport};
+
+pub struct Client {
+    middlewares: Vec<Box<dyn Middleware>>,
+    default_headers: HashMap<String, String>,
+    pooling: bool,
+    idle_timeout: Option<Duration>,
+    close_timeout: Duration,
+    max_concurrent_per_host: Option<usize>,
+    retries: usize,
+    retry_backoff: Duration,
+    max_retry_delay: Duration,
+    pool: Pool,
+    limiter: Limiter,
+}
+
+impl Client {
+
+    pub fn default() -> Self {
+        Self {
+            middlewares: Vec::new(),
+            default_headers: HashMap::with_hasher(RandomState::new()),
+            pooling: false,
+            idle_timeout: None,
+            close_timeout: Duration::from_secs(30),
+            max_concurrent_per_host: None,
+            retries: 0,
+            retry_backoff: Duration::from_millis(100),
+            max_retry_delay: Duration::from_secs(60),
+            pool: Pool::new(),
+            limiter: Limiter::new(),
+        }
+    }
+
+    pub fn middlewares(&self) -> &Vec<Box<dyn Middleware>> {
+        &self.middlewares
+    }
+
+    pub fn default_headers(&self) -> &HashMap<String, String> {
+        &self.default_headers
+    }
+
+    pub fn default_headers_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.default_headers
+    }
+
+    pub fn default_header<N: Into<String>>(&self, name: N) -> Option<&String> {
+        self.default_headers.get(&name.into())
+    }
+
+    pub fn pooling(&self) -> bool {
+        self.pooling
+    }
+
+    pub fn idle_timeout(&self) -> &Option<Duration> {
+        &self.idle_timeout
+    }
+
+    pub fn close_timeout(&self) -> &Duration {
+        &self.close_timeout
+    }
+
+    pub fn max_concurrent_per_host(&self) -> &Option<usize> {
+        &self.max_concurrent_per_host
+    }
+
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    pub fn retry_backoff(&self) -> &Duration {
+        &self.retry_backoff
+    }
+
+    pub fn max_retry_delay(&self) -> &Duration {
+        &self.max_retry_delay
+    }
+
+    pub fn in_flight<H: Into<String>>(&self, host: H) -> usize {
+        self.limiter.active(&host.into().to_ascii_lowercase())
+    }
+
+    pub fn idle_connections(&self) -> usize {
+        self.pool.len(self.idle_timeout)
+    }
+
+    pub fn has_middlewares(&self) -> bool {
+        !self.middlewares.is_empty()
+    }
+
+    pub fn add_middleware<M: Middleware + 'static>(&mut self, middleware: M) {
+        self.middlewares.push(Box::new(middleware));
+    }
+
+    pub fn set_default_header<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        self.default_headers.insert(name.into(), value.into());
+    }
+
+    pub fn set_pooling(&mut self, value: bool) {
+        self.pooling = value;
+    }
+
+    pub fn set_idle_timeout(&mut self, value: Duration) {
+        self.idle_timeout = Some(value);
+    }
+
+    pub fn set_close_timeout(&mut self, value: Duration) {
+        self.close_timeout = value;
+    }
+
+    pub fn set_max_concurrent_per_host(&mut self, value: usize) {
+        self.max_concurrent_per_host = Some(value);
+    }
+
+    pub fn set_retries(&mut self, value: usize) {
+        self.retries = value;
+    }
+
+    pub fn set_retry_backoff(&mut self, value: Duration) {
+        self.retry_backoff = value;
+    }
+
+    pub fn set_max_retry_delay(&mut self, value: Duration) {
+        self.max_retry_delay = value;
+    }
+
+    pub fn remove_max_concurrent_per_host(&mut self) {
+        self.max_concurrent_per_host = None;
+    }
+
+    pub fn remove_idle_timeout(&mut self) {
+        self.idle_timeout = None;
+    }
+
+    pub fn remove_default_header<N: Into<String>>(&mut self, name: N) {
+        self.default_headers.remove(&name.into());
+    }
+
+    pub fn clear_middlewares(&mut self) {
+        self.middlewares.clear();
+    }
+
+    pub fn clear_default_headers(&mut self) {
+        self.default_headers.clear();
+    }
+
+    pub async fn connect(&self, req: &mut Request) -> Result<Connection, Error> {
+        Connection::open(req).await
+    }
+
+    pub async fn send<'a>(&self, req: &mut Request) -> Result<Response<'a>, Error> {
+        if let Some(res) = self.before_send(req)? {
+            return Ok(res);
+        }
+        let deadline = req.deadline_at(); // one budget for every attempt and the waits between them
+        let mut attempt = 0;
+        loop {
+            let result = self.send_once(req, deadline).await;
+            let delay = match self.retry_delay(req, &result, attempt) {
+                Some(delay) => delay,
+                None => return self.after_send(result?),
+            };
+            drop(result); // frees the connection and the per-host slot while waiting
+            match deadline {
+                Some(deadline) => {
+                    task::sleep(delay.min(deadline.saturating_duration_since(Instant::now()))).await;
+                    if Instant::now() >= deadline {
+                        return Err(Error::new(ErrorKind::TimedOut, "The request deadline has been exceeded."));
+                    }
+                },
+                None => task::sleep(delay).await,
+            };
+            attempt += 1;
+        }
+    }
+
+    async fn send_once<'a>(&self, req: &mut Request, deadline: Option<Instant>) -> Result<Response<'a>, Error> {
+        let permit = self.limiter.acquire(host_key(req), self.max_concurrent_per_host).await;
+        let mut res = req.send_via(self.transport(), deadline).await?;
+        res.set_permit(permit); // held until the body is read or the response dropped
+        Ok(res)
+    }
+
+    fn retry_delay(&self, req: &Request, result: &Result<Response<'_>, Error>, attempt: usize) -> Option<Duration> {
+        if attempt >= self.retries || !req.is_idempotent() {
+            return None;
+        }
+
+        let backoff = self.retry_backoff * 2u32.pow(attempt.min(16) as u32);
+        let delay = match result {
+            Ok(res) if res.has_status(Status::TooManyRequests) || res.has_status(Status::ServiceUnavailable) => match res.retry_after() {
+                Some(delay) => delay,
+                None => backoff,
+            },
+            Ok(res) if res.has_status(Status::BadGateway) || res.has_status(Status::GatewayTimeout) => backoff,
+            Err(e) if is_transient(e) => backoff,
+            _ => return None,
+        };
+        Some(delay.min(self.max_retry_delay))
+    }
+
+    pub async fn send_stream<'a, R>(&self, req: &mut Request, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        if let Some(res) = self.before_send(req)? {
+            return Ok(res);
+        }
+        let permit = self.limiter.acquire(host_key(req), self.max_concurrent_per_host).await;
+        let mut res = req.send_stream_via(body, self.transport(), req.deadline_at()).await?;
+        res.set_permit(permit);
+        self.after_send(res)
+    }
+
+    pub async fn close(self) {
+        let deadline = Instant::now() + self.close_timeout;
+        while self.pool.in_flight() > 0 && Instant::now() < deadline { // responses still being read
+            task::sleep(Duration::from_millis(10)).await;
+        }
+        self.pool.close().await;
+    }
+
+    fn transport(&self) -> Transport<'_> {
+        match self.pooling {
+            true => Transport::Pooled(&self.pool, self.idle_timeout),
+            false => Transport::Direct,
+        }
+    }
+
+    fn before_send<'a>(&self, req: &mut Request) -> Result<Option<Response<'a>>, Error> {
+        self.merge_default_headers(req);
+        for middleware in self.middlewares.iter() {
+            if let Some(res) = middleware.on_request(req)? {
+                return Ok(Some(res));
+            }
+        }
+        Ok(None)
+    }
+
+    fn merge_default_headers(&self, req: &mut Request) {
+        for (name, value) in self.default_headers.iter() {
+            if !req.headers().keys().any(|header| header.eq_ignore_ascii_case(name)) { // request headers win
+                req.set_header(name, value);
+            }
+        }
+    }
+
+    fn after_send<'a>(&self, res: Response<'a>) -> Result<Response<'a>, Error> {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.on_response(&res)?;
+        }
+        Ok(res)
+    }
+}
+
+fn host_key(req: &Request) -> String {
+    req.url().host_str().unwrap_or("localhost").to_ascii_lowercase()
+}
+
+fn is_transient(e: &Error) -> bool {
+    match e.kind() {
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::TimedOut => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use flate2::Compression;
+use flate2::read::{GzDecoder, ZlibDecoder, DeflateDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::write::{GzDecoder as GzSink, ZlibDecoder as ZlibSink, DeflateDecoder as DeflateSink};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Codec {
+    Gzip,
+    Deflate,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Codec::Gzip => write!(fmt, "gzip"),
+            Codec::Deflate => write!(fmt, "deflate"),
+        }
+    }
+}
+
+pub fn decode_body(encoding: &str, data: &[u8], limit: Option<usize>) -> Result<Option<Vec<u8>>, Error> {
+    let mut output = Vec::new();
+
+    let result = match encoding.trim().to_ascii_lowercase().as_str() {
+        "gzip" | "x-gzip" => read_limited(GzDecoder::new(data), &mut output, limit),
+        "deflate" => match read_limited(ZlibDecoder::new(data), &mut output, limit) {
+            Ok(size) => Ok(size),
+            Err(_) => { // raw deflate without zlib wrapper
+                output.clear();
+                read_limited(DeflateDecoder::new(data), &mut output, limit)
+            },
+        },
+        _ => return Ok(None),
+    };
+
+    match result {
+        Ok(_) => match limit {
+            Some(limit) if output.len() > limit => Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while decoding the HTTP body data.", limit))),
+            _ => Ok(Some(output)),
+        },
+        Err(e) => Err(decode_error(encoding, e)),
+    }
+}
+
+fn read_limited<R: Read>(reader: R, output: &mut Vec<u8>, limit: Option<usize>) -> Result<usize, Error> {
+    match limit {
+        Some(limit) => reader.take(limit as u64 + 1).read_to_end(output), // stop right after the limit
+        None => reader.read_to_end(output),
+    }
+}
+
+pub enum Decoder {
+    Pending(Vec<u8>),
+    Gzip(GzSink<Vec<u8>>),
+    Zlib(ZlibSink<Vec<u8>>),
+    Deflate(DeflateSink<Vec<u8>>),
+}
+
+impl Decoder {
+
+    pub fn new(encoding: &str) -> Option<Self> {
+        match encoding.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Decoder::Gzip(GzSink::new(Vec::new()))),
+            "deflate" => Some(Decoder::Pending(Vec::new())), // wrapper known after two bytes
+            _ => None,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        if let Decoder::Pending(head) = self {
+            head.extend_from_slice(data);
+            if head.len() < 2 {
+                return Ok(Vec::new());
+            }
+            let head = std::mem::take(head);
+            let zlib = head[0] & 0x0f == 8 && (u16::from(head[0]) << 8 | u16::from(head[1])) % 31 == 0;
+            *self = match zlib {
+                true => Decoder::Zlib(ZlibSink::new(Vec::new())),
+                false => Decoder::Deflate(DeflateSink::new(Vec::new())), // raw deflate without zlib wrapper
+            };
+            return self.push(&head);
+        }
+
+        let result = match self {
+            Decoder::Gzip(sink) => sink.write_all(data),
+            Decoder::Zlib(sink) => sink.write_all(data),
+            Decoder::Deflate(sink) => sink.write_all(data),
+            Decoder::Pending(..) => Ok(()),
+        };
+        result.map_err(|e| decode_error(self.name(), e))?;
+        Ok(self.take())
+    }
+
+    pub fn finish(&mut self) -> Result<Vec<u8>, Error> {
+        let result = match self {
+            Decoder::Gzip(sink) => sink.try_finish(),
+            Decoder::Zlib(sink) => sink.try_finish(),
+            Decoder::Deflate(sink) => sink.try_finish(),
+            Decoder::Pending(head) if head.is_empty() => Ok(()),
+            Decoder::Pending(..) => Err(Error::new(ErrorKind::UnexpectedEof, "the stream is truncated")),
+        };
+        result.map_err(|e| decode_error(self.name(), e))?;
+        Ok(self.take())
+    }
+
+    fn take(&mut self) -> Vec<u8> {
+        match self {
+            Decoder::Gzip(sink) => std::mem::take(sink.get_mut()),
+            Decoder::Zlib(sink) => std::mem::take(sink.get_mut()),
+            Decoder::Deflate(sink) => std::mem::take(sink.get_mut()),
+            Decoder::Pending(..) => Vec::new(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Decoder::Gzip(_) => "gzip",
+            _ => "deflate",
+        }
+    }
+}
+
+fn decode_error(encoding: &str, e: Error) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("The `{}` body could not be decoded: {}.", encoding, e))
+}
+
+pub fn encode_body(codec: Codec, data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut sink = Sink::new(codec);
+    sink.write_all(data)?;
+    sink.try_finish()?;
+    Ok(std::mem::take(sink.get_mut()))
+}
+
+enum Sink {
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+impl Sink {
+
+    fn new(codec: Codec) -> Self {
+        match codec {
+            Codec::Gzip => Sink::Gzip(GzEncoder::new(Vec::new(), Compression::default())),
+            Codec::Deflate => Sink::Deflate(ZlibEncoder::new(Vec::new(), Compression::default())),
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        match self {
+            Sink::Gzip(encoder) => encoder.write_all(data),
+            Sink::Deflate(encoder) => encoder.write_all(data),
+        }
+    }
+
+    fn try_finish(&mut self) -> Result<(), Error> {
+        match self {
+            Sink::Gzip(encoder) => encoder.try_finish(),
+            Sink::Deflate(encoder) => encoder.try_finish(),
+        }
+    }
+
+    fn get_mut(&mut self) -> &mut Vec<u8> {
+        match self {
+            Sink::Gzip(encoder) => encoder.get_mut(),
+            Sink::Deflate(encoder) => encoder.get_mut(),
+        }
+    }
+}
+
+pub struct Encoder<R> {
+    inner: R,
+    sink: Sink,
+    output: Vec<u8>,
+    position: usize,
+    done: bool,
+}
+
+impl<R> Encoder<R> {
+
+    pub fn new(inner: R, codec: Codec) -> Self {
+        Self {
+            inner,
+            sink: Sink::new(codec),
+            output: Vec::new(),
+            position: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: async_std::io::Read + Unpin> async_std::io::Read for Encoder<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let this = &mut *self;
+        let mut chunk = [0u8; 8192];
+
+        loop {
+            if this.position < this.output.len() {
+                let size = buf.len().min(this.output.len() - this.position);
+                buf[..size].copy_from_slice(&this.output[this.position..this.position + size]);
+                this.position += size;
+                return Poll::Ready(Ok(size));
+            } else if this.done {
+                return Poll::Ready(Ok(0));
+            }
+
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk) {
+                Poll::Ready(Ok(0)) => {
+                    this.sink.try_finish()?;
+                    this.done = true;
+                },
+                Poll::Ready(Ok(size)) => this.sink.write_all(&chunk[..size])?,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+            this.output = std::mem::take(this.sink.get_mut()); // compressed so far
+            this.position = 0;
+        }
+    }
+}
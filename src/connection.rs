@@ -0,0 +1,260 @@
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+use async_std::io::{Read, Write};
+use async_std::future::poll_fn;
+use crate::{Method, Status, Request, Response};
+
+pub trait Duplex: Read + Write + Send + Unpin {}
+
+impl<T: Read + Write + Send + Unpin> Duplex for T {}
+
+const HEALTH_CHECK_AFTER: u64 = 1000;
+const IDLE: u8 = 0;
+const BUSY: u8 = 1;
+const BROKEN: u8 = 2;
+const CLOSING: u8 = 3;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ConnectionState(Arc<AtomicU8>);
+
+impl ConnectionState {
+
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU8::new(IDLE)))
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == IDLE
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == BROKEN
+    }
+
+    pub fn set_idle(&self) {
+        if self.0.compare_exchange(BUSY, IDLE, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            let _ = self.0.compare_exchange(CLOSING, BROKEN, Ordering::SeqCst, Ordering::SeqCst); // peer closes after the body
+        }
+    }
+
+    pub fn set_busy(&self) {
+        let _ = self.0.compare_exchange(IDLE, BUSY, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    pub fn set_closing(&self) {
+        let _ = self.0.compare_exchange(BUSY, CLOSING, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    pub fn set_broken(&self) {
+        self.0.store(BROKEN, Ordering::SeqCst);
+    }
+}
+
+pub struct Connection {
+    stream: Box<dyn Duplex>,
+    state: ConnectionState,
+    last_used: Instant,
+    remote_addr: Option<SocketAddr>,
+    requests: usize,
+    persistent: bool,
+}
+
+impl Connection {
+
+    pub async fn open(req: &mut Request) -> Result<Self, Error> {
+        Ok(Self {
+            stream: req.open_conn().await?,
+            state: ConnectionState::new(),
+            last_used: Instant::now(),
+            remote_addr: *req.remote_addr(),
+            requests: 0,
+            persistent: true,
+        })
+    }
+
+    pub fn last_used(&self) -> &Instant {
+        &self.last_used
+    }
+
+    pub fn remote_addr(&self) -> &Option<SocketAddr> {
+        &self.remote_addr
+    }
+
+    pub fn is_drained(&self) -> bool {
+        self.state.is_idle()
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.state.is_broken()
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    pub async fn is_alive(&mut self) -> bool {
+        if self.is_broken() {
+            return false;
+        }
+
+        let stream = &mut self.stream;
+        let mut byte = [0u8; 1];
+        poll_fn(|cx| match Pin::new(&mut **stream).poll_read(cx, &mut byte) {
+            Poll::Pending => Poll::Ready(true),
+            Poll::Ready(_) => Poll::Ready(false), // closed, reset or unexpected data
+        }).await
+    }
+
+    pub async fn send<'a>(&'a mut self, req: &mut Request) -> Result<Response<'a>, Error> {
+        req.update_headers(false);
+        self.exchange(req, &mut "".as_bytes(), true).await
+    }
+
+    pub async fn send_stream<'a, R>(&'a mut self, req: &mut Request, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        req.update_headers(true);
+        self.exchange(req, body, false).await
+    }
+
+    async fn redial(&mut self, req: &mut Request) -> Result<(), Error> {
+        self.stream = req.open_conn().await?;
+        self.state = ConnectionState::new();
+        self.remote_addr = *req.remote_addr();
+        self.requests = 0;
+        self.persistent = true;
+        Ok(())
+    }
+
+    pub(crate) fn stream_mut(&mut self) -> &mut Box<dyn Duplex> {
+        &mut self.stream
+    }
+
+    pub(crate) fn touch(&mut self) {
+        self.last_used = Instant::now();
+    }
+
+    async fn exchange<'a, R>(&'a mut self, req: &mut Request, body: &mut R, replayable: bool) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        let mut res = self.dispatch(req, body, replayable).await?;
+        req.attach(&mut res, &mut self.stream);
+        Ok(res)
+    }
+
+    pub(crate) async fn dispatch<'a, R>(&mut self, req: &mut Request, body: &mut R, replayable: bool) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        if self.is_broken() {
+            return Err(Error::new(ErrorKind::NotConnected, "The connection is in an indeterminate state and cannot be reused."));
+        } else if !self.is_drained() {
+            return Err(Error::new(ErrorKind::InvalidInput, "The previous response body has not been read."));
+        }
+
+        if self.last_used.elapsed() >= Duration::from_millis(HEALTH_CHECK_AFTER) && !self.is_alive().await {
+            self.redial(req).await?;
+        }
+        self.state.set_busy();
+        self.last_used = Instant::now();
+
+        let mut res = match req.transmit(&mut self.stream, body).await {
+            Ok(res) => res,
+            Err(e) if self.requests > 0 && replayable && req.is_idempotent() && is_stale(&e) => { // closed while idle
+                self.redial(req).await?;
+                self.state.set_busy();
+                match req.transmit(&mut self.stream, &mut "".as_bytes()).await {
+                    Ok(res) => res,
+                    Err(e) => {
+                        self.state.set_broken();
+                        return Err(e);
+                    },
+                }
+            },
+            Err(e) => {
+                self.state.set_broken();
+                return Err(e);
+            },
+        };
+        res.set_connection_state(self.state.clone());
+        res.set_reused(self.requests > 0);
+        if let Some(addr) = self.remote_addr {
+            res.set_remote_addr(addr);
+        }
+        self.requests += 1;
+        self.persistent = res.is_persistent() && !req.has_connection_token("close");
+        if !self.persistent {
+            self.state.set_closing();
+        }
+
+        if res.is_switching() {
+            self.state.set_broken(); // no longer speaks HTTP
+        } else if req.has_method(Method::Head) || res.has_status(Status::NoContent) || res.has_status(Status::NotModified) {
+            self.state.set_idle();
+        }
+        Ok(res)
+    }
+}
+
+fn is_stale(e: &Error) -> bool {
+    match e.kind() {
+        ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe | ErrorKind::UnexpectedEof => true,
+        _ => false,
+    }
+}
+
+pub struct ReadHalf<'a> {
+    inner: Arc<Mutex<Box<dyn Duplex + 'a>>>,
+}
+
+pub struct WriteHalf<'a> {
+    inner: Arc<Mutex<Box<dyn Duplex + 'a>>>,
+}
+
+pub(crate) fn split<'a>(stream: Box<dyn Duplex + 'a>) -> (ReadHalf<'a>, WriteHalf<'a>) {
+    let inner = Arc::new(Mutex::new(stream));
+    (ReadHalf { inner: inner.clone() }, WriteHalf { inner })
+}
+
+impl<'a> Read for ReadHalf<'a> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let mut stream = match self.inner.lock() {
+            Ok(stream) => stream,
+            Err(_) => return Poll::Ready(Err(Error::new(ErrorKind::Other, "The stream lock is poisoned."))),
+        };
+        Pin::new(&mut **stream).poll_read(cx, buf)
+    }
+}
+
+impl<'a> Write for WriteHalf<'a> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let mut stream = match self.inner.lock() {
+            Ok(stream) => stream,
+            Err(_) => return Poll::Ready(Err(Error::new(ErrorKind::Other, "The stream lock is poisoned."))),
+        };
+        Pin::new(&mut **stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let mut stream = match self.inner.lock() {
+            Ok(stream) => stream,
+            Err(_) => return Poll::Ready(Err(Error::new(ErrorKind::Other, "The stream lock is poisoned."))),
+        };
+        Pin::new(&mut **stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let mut stream = match self.inner.lock() {
+            Ok(stream) => stream,
+            Err(_) => return Poll::Ready(Err(Error::new(ErrorKind::Other, "The stream lock is poisoned."))),
+        };
+        Pin::new(&mut **stream).poll_close(cx)
+    }
+}
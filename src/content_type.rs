@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::str::FromStr;
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentType {
+    essence: String,
+    params: HashMap<String, String>,
+}
+
+impl ContentType {
+
+    pub fn essence(&self) -> &str {
+        &self.essence
+    }
+
+    pub fn main_type(&self) -> &str {
+        match self.essence.split_once('/') {
+            Some((main, _)) => main,
+            None => &self.essence,
+        }
+    }
+
+    pub fn sub_type(&self) -> &str {
+        match self.essence.split_once('/') {
+            Some((_, sub)) => sub,
+            None => "",
+        }
+    }
+
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    pub fn param<N: Into<String>>(&self, name: N) -> Option<&String> {
+        self.params.get(&name.into().to_ascii_lowercase())
+    }
+
+    pub fn charset(&self) -> Option<&String> {
+        self.param("charset")
+    }
+
+    pub fn has_essence(&self, value: &str) -> bool {
+        self.essence.eq_ignore_ascii_case(value)
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Error> {
+        let mut parts = value.split(';');
+        let essence = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        match essence.split_once('/') {
+            Some((main, sub)) if !main.is_empty() && !sub.is_empty() => (),
+            _ => return Err(Error::new(ErrorKind::InvalidInput, format!("The content type `{}` is invalid.", value))),
+        };
+
+        let mut params = HashMap::with_hasher(RandomState::new());
+        for param in parts {
+            if let Some((name, value)) = param.split_once('=') {
+                params.insert(name.trim().to_ascii_lowercase(), value.trim().trim_matches('"').to_string());
+            }
+        }
+
+        Ok(Self { essence, params })
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.essence)?;
+        for (name, value) in self.params.iter() {
+            write!(fmt, "; {}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
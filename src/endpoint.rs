@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::io::{Error, ErrorKind};
+use url::Url;
+use crate::{Method, Request};
+
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    base_url: Url,
+    headers: HashMap<String, String>,
+}
+
+impl Endpoint {
+
+    pub fn new<U: Into<String>>(base_url: U) -> Result<Self, Error> {
+        let base_url = base_url.into();
+        let mut url = match Url::parse(&base_url) {
+            Ok(url) if !url.cannot_be_a_base() => url,
+            _ => return Err(Error::new(ErrorKind::InvalidInput, format!("The base URL `{}` is invalid.", base_url))),
+        };
+        if !url.path().ends_with('/') { // join below the last segment, not in place of it
+            url.set_path(&format!("{}/", url.path()));
+        }
+
+        Ok(Self {
+            base_url: url,
+            headers: HashMap::with_hasher(RandomState::new()),
+        })
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    pub fn header<N: Into<String>>(&self, name: N) -> Option<&String> {
+        self.headers.get(&name.into())
+    }
+
+    pub fn url<P: AsRef<str>>(&self, path: P) -> Result<Url, Error> {
+        let path = path.as_ref();
+        match self.base_url.join(path.trim_start_matches('/')) {
+            Ok(url) => Ok(url),
+            Err(_) => Err(Error::new(ErrorKind::InvalidInput, format!("The path `{}` is invalid.", path))),
+        }
+    }
+
+    pub fn request<P: AsRef<str>>(&self, method: Method, path: P) -> Result<Request, Error> {
+        let mut req = Request::default();
+        req.set_url(self.url(path)?);
+        req.set_method(method);
+        for (name, value) in self.headers.iter() {
+            req.set_header(name, value);
+        }
+        Ok(req)
+    }
+
+    pub fn get<P: AsRef<str>>(&self, path: P) -> Result<Request, Error> {
+        self.request(Method::Get, path)
+    }
+
+    pub fn post<P: AsRef<str>>(&self, path: P) -> Result<Request, Error> {
+        self.request(Method::Post, path)
+    }
+
+    pub fn put<P: AsRef<str>>(&self, path: P) -> Result<Request, Error> {
+        self.request(Method::Put, path)
+    }
+
+    pub fn patch<P: AsRef<str>>(&self, path: P) -> Result<Request, Error> {
+        self.request(Method::Patch, path)
+    }
+
+    pub fn delete<P: AsRef<str>>(&self, path: P) -> Result<Request, Error> {
+        self.request(Method::Delete, path)
+    }
+
+    pub fn has_header<N: Into<String>>(&self, name: N) -> bool {
+        self.headers.contains_key(&name.into())
+    }
+
+    pub fn set_header<N: Into<String>, V: Into<String>>(&mut self, name: N, value: V) {
+        self.headers.insert(name.into(), value.into());
+    }
+
+    pub fn remove_header<N: Into<String>>(&mut self, name: N) {
+        self.headers.remove(&name.into());
+    }
+}
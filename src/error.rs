@@ -0,0 +1,26 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    UnableToConnect(io::Error),
+    ConnectTimedOut,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnableToConnect(e) => write!(fmt, "The connection to the server could not be established: {}.", e),
+            Error::ConnectTimedOut => write!(fmt, "The connect timeout has been exceeded."),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::UnableToConnect(e) => Some(e),
+            Error::ConnectTimedOut => None,
+        }
+    }
+}
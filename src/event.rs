@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+
+    pub fn id(&self) -> &Option<String> {
+        &self.id
+    }
+
+    pub fn event(&self) -> &Option<String> {
+        &self.event
+    }
+
+    pub fn data(&self) -> &String {
+        &self.data
+    }
+
+    pub fn retry(&self) -> &Option<Duration> {
+        &self.retry
+    }
+
+    pub(crate) fn with_id(id: Option<String>) -> Self {
+        Self {
+            id,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn read_field(&mut self, line: &str) {
+        if line.starts_with(':') { // comment
+            return;
+        }
+
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match name {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            },
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) => {
+                self.retry = value.parse::<u64>().ok().map(Duration::from_millis);
+            },
+            _ => {},
+        };
+    }
+
+    pub(crate) fn finish(&mut self) {
+        if self.data.ends_with('\n') {
+            self.data.pop();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use async_std::io::{Read, ReadExt, Write};
+use async_std::future::poll_fn;
+use async_std::task;
+use bytes::Bytes;
+use h2::{RecvStream, SendStream};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+use url::Position;
+use crate::{Request, Response, Method, Version};
+
+const CONNECTION_HEADERS: [&str; 7] = ["Connection", "Expect", "Host", "Keep-Alive", "Proxy-Connection", "Transfer-Encoding", "Upgrade"];
+
+pub async fn send_h2<'a, S, R>(req: &Request, stream: S, body: &mut R) -> Result<Response<'a>, Error>
+    where
+    S: Read + Write + Send + Unpin + 'static,
+    R: Read + Send + Unpin,
+{
+    let (client, conn) = h2::client::handshake(stream.compat()).await.map_err(h2_error)?;
+    task::spawn(async move {
+        let _ = conn.await;
+    });
+    let mut client = client.ready().await.map_err(h2_error)?;
+
+    let uri = match req.has_method(Method::Connect) {
+        true => req.uri(), // authority-form
+        false => format!("{}://{}{}", req.url().scheme(), &req.url()[Position::BeforeHost..Position::AfterPort], req.uri()), // no userinfo or fragment
+    };
+    let mut builder = http::Request::builder()
+        .method(req.method().to_string().as_str())
+        .uri(uri.as_str());
+    for (name, value) in req.headers().iter() {
+        if !CONNECTION_HEADERS.iter().any(|header| header.eq_ignore_ascii_case(name)) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    let request = match builder.body(()) {
+        Ok(request) => request,
+        Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e.to_string())),
+    };
+
+    let empty = (!req.has_header("Content-Length") && !req.has_header("Transfer-Encoding"))
+        || req.has_method(Method::Trace) || req.has_method(Method::Connect);
+    let (response, mut send) = client.send_request(request, empty).map_err(h2_error)?;
+    if !empty {
+        send_body(&mut send, body, req.chunk_size()).await?;
+    }
+
+    let (parts, body) = response.await.map_err(h2_error)?.into_parts();
+    let mut res = Response::with_reader(RecvBody::new(body));
+    res.set_version(Version::Http2_0);
+    res.set_status_str(parts.status.as_str())?;
+    res.set_request_method(req.method().clone());
+    for (name, value) in parts.headers.iter() {
+        match value.to_str() {
+            Ok(value) => res.set_header(canonical_name(name.as_str()), value),
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData, format!("The response header `{}` is invalid.", name))),
+        };
+    }
+    Ok(res)
+}
+
+async fn send_body<R>(send: &mut SendStream<Bytes>, body: &mut R, chunk_size: usize) -> Result<(), Error>
+    where
+    R: Read + Send + Unpin,
+{
+    let mut buffer = vec![0u8; chunk_size];
+    loop {
+        let size = body.read(&mut buffer).await?;
+        if size == 0 {
+            break;
+        }
+
+        let mut data = Bytes::copy_from_slice(&buffer[..size]);
+        while !data.is_empty() {
+            send.reserve_capacity(data.len());
+            let capacity = match poll_fn(|cx| send.poll_capacity(cx)).await {
+                Some(Ok(capacity)) => capacity,
+                Some(Err(e)) => return Err(h2_error(e)),
+                None => return Err(Error::new(ErrorKind::BrokenPipe, "The HTTP/2 stream closed before the request body was sent.")),
+            };
+            if capacity > 0 { // never more than the peer's window allows
+                send.send_data(data.split_to(capacity.min(data.len())), false).map_err(h2_error)?;
+            }
+        }
+    }
+    send.send_data(Bytes::new(), true).map_err(h2_error)
+}
+
+fn canonical_name(name: &str) -> String {
+    name.split('-').map(|part| {
+        let mut chars = part.chars();
+        match chars.next() {
+            Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+            None => String::new(),
+        }
+    }).collect::<Vec<String>>().join("-")
+}
+
+fn h2_error(e: h2::Error) -> Error {
+    let message = e.to_string();
+    match e.into_io() {
+        Some(e) => e,
+        None => Error::new(ErrorKind::Other, message),
+    }
+}
+
+struct RecvBody {
+    stream: RecvStream,
+    buffer: Bytes,
+}
+
+impl RecvBody {
+
+    fn new(stream: RecvStream) -> Self {
+        Self {
+            stream,
+            buffer: Bytes::new(),
+        }
+    }
+}
+
+impl Read for RecvBody {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        while self.buffer.is_empty() {
+            match self.stream.poll_data(cx) {
+                Poll::Ready(Some(Ok(data))) => {
+                    let _ = self.stream.flow_control().release_capacity(data.len());
+                    self.buffer = data;
+                },
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(h2_error(e))),
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let size = buf.len().min(self.buffer.len());
+        buf[..size].copy_from_slice(&self.buffer.split_to(size));
+        Poll::Ready(Ok(size))
+    }
+}
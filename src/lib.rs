@@ -1,11 +1,54 @@
+mod chunked;
+mod client;
+#[cfg(feature = "compression")]
+mod compression;
+mod connection;
+mod content_type;
+mod endpoint;
+mod error;
+mod event;
+#[cfg(feature = "http2")]
+mod http2;
+mod limiter;
+mod middleware;
+mod pool;
 mod request;
 mod response;
+mod retry;
+mod socks;
+mod tap;
+mod timing;
+mod tls;
 mod utils;
+mod websocket;
 
+pub use client::*;
+pub use connection::*;
+pub use content_type::*;
+pub use endpoint::*;
+pub use error::*;
+pub use event::*;
+pub use middleware::*;
 pub use request::*;
 pub use response::*;
+pub use timing::*;
+pub use websocket::*;
+#[cfg(feature = "compression")]
+pub use compression::Codec;
 pub use async_httplib::{Method, Version, Status};
 pub use url::{Url, Position};
+pub use utils::websocket_accept;
+use chunked::*;
+use limiter::*;
+use pool::*;
+use retry::*;
+use socks::*;
+use tap::*;
+use tls::*;
 use utils::*;
+#[cfg(feature = "compression")]
+use compression::*;
 
 
+#[cfg(feature = "http2")]
+use http2::*;
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+use async_std::future::poll_fn;
+
+#[derive(Default)]
+struct Slot {
+    active: usize,
+    waiters: Vec<Waker>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Limiter {
+    slots: Arc<Mutex<HashMap<String, Slot>>>,
+}
+
+impl Limiter {
+
+    pub fn new() -> Self {
+        Self {
+            slots: Arc::new(Mutex::new(HashMap::with_hasher(RandomState::new()))),
+        }
+    }
+
+    pub async fn acquire(&self, key: String, limit: Option<usize>) -> Permit {
+        poll_fn(|cx| {
+            let mut slots = match self.slots.lock() {
+                Ok(slots) => slots,
+                Err(_) => return Poll::Ready(()),
+            };
+            let slot = slots.entry(key.clone()).or_insert_with(Slot::default);
+            match limit {
+                Some(limit) if slot.active >= limit => {
+                    slot.waiters.push(cx.waker().clone());
+                    Poll::Pending
+                },
+                _ => {
+                    slot.active += 1;
+                    Poll::Ready(())
+                },
+            }
+        }).await;
+
+        Permit {
+            slots: self.slots.clone(),
+            key,
+        }
+    }
+
+    pub fn active(&self, key: &str) -> usize {
+        match self.slots.lock() {
+            Ok(slots) => slots.get(key).map_or(0, |slot| slot.active),
+            Err(_) => 0,
+        }
+    }
+}
+
+pub(crate) struct Permit {
+    slots: Arc<Mutex<HashMap<String, Slot>>>,
+    key: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut slots = match self.slots.lock() {
+            Ok(slots) => slots,
+            Err(_) => return,
+        };
+        if let Some(slot) = slots.get_mut(&self.key) {
+            slot.active -= 1;
+            for waker in slot.waiters.drain(..) { // waiters race for the free slot
+                waker.wake();
+            }
+            if slot.active == 0 {
+                slots.remove(&self.key);
+            }
+        }
+    }
+}
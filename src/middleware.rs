@@ -0,0 +1,13 @@
+use std::io::Error;
+use crate::{Request, Response};
+
+pub trait Middleware: Send + Sync {
+
+    fn on_request(&self, _req: &mut Request) -> Result<Option<Response<'static>>, Error> {
+        Ok(None)
+    }
+
+    fn on_response(&self, _res: &Response) -> Result<(), Error> {
+        Ok(())
+    }
+}
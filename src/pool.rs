@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::io::Error;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use async_std::io::{Read, Write};
+use async_std::prelude::*;
+use crate::Connection;
+
+#[derive(Clone)]
+pub(crate) struct Pool {
+    idle: Arc<Mutex<HashMap<String, Vec<Connection>>>>,
+    in_flight: Arc<AtomicUsize>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Pool {
+
+    pub fn new() -> Self {
+        Self {
+            idle: Arc::new(Mutex::new(HashMap::with_hasher(RandomState::new()))),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn checkout(&self, key: &str, timeout: Option<Duration>) -> Option<Connection> {
+        let mut idle = self.idle.lock().ok()?;
+        evict(&mut idle, timeout);
+        idle.get_mut(key)?.pop() // most recently used first
+    }
+
+    pub fn checkin(&self, key: String, conn: Connection) {
+        if self.closed.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.entry(key).or_insert_with(Vec::new).push(conn);
+        }
+    }
+
+    pub fn len(&self, timeout: Option<Duration>) -> usize {
+        match self.idle.lock() {
+            Ok(mut idle) => {
+                evict(&mut idle, timeout);
+                idle.values().map(|conns| conns.len()).sum()
+            },
+            Err(_) => 0,
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let idle = match self.idle.lock() {
+            Ok(mut idle) => std::mem::take(&mut *idle),
+            Err(_) => return,
+        };
+        for mut conn in idle.into_values().flatten() {
+            let _ = conn.stream_mut().close().await;
+        }
+    }
+}
+
+fn evict(idle: &mut HashMap<String, Vec<Connection>>, timeout: Option<Duration>) {
+    for conns in idle.values_mut() {
+        conns.retain(|conn| !conn.is_broken() && timeout.map_or(true, |timeout| conn.last_used().elapsed() < timeout));
+    }
+    idle.retain(|_, conns| !conns.is_empty());
+}
+
+pub(crate) struct Pooled {
+    conn: Option<Connection>,
+    pool: Pool,
+    key: String,
+}
+
+impl Pooled {
+
+    pub fn new(conn: Connection, pool: Pool, key: String) -> Self {
+        pool.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self {
+            conn: Some(conn),
+            pool,
+            key,
+        }
+    }
+}
+
+impl Drop for Pooled {
+    fn drop(&mut self) {
+        self.pool.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if let Some(mut conn) = self.conn.take() {
+            if conn.is_drained() { // body fully read, safe to reuse
+                conn.touch();
+                self.pool.checkin(std::mem::take(&mut self.key), conn);
+            }
+        }
+    }
+}
+
+impl Read for Pooled {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        match self.conn.as_mut() {
+            Some(conn) => Pin::new(&mut **conn.stream_mut()).poll_read(cx, buf),
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+}
+
+impl Write for Pooled {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        match self.conn.as_mut() {
+            Some(conn) => Pin::new(&mut **conn.stream_mut()).poll_write(cx, buf),
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match self.conn.as_mut() {
+            Some(conn) => Pin::new(&mut **conn.stream_mut()).poll_flush(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match self.conn.as_mut() {
+            Some(conn) => Pin::new(&mut **conn.stream_mut()).poll_close(cx),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
@@ -3,14 +3,96 @@ use std::collections::HashMap;
 use std::collections::hash_map::RandomState;
 use std::io::{Error, ErrorKind};
 use std::str::FromStr;
-use url::{Url, Position};
-use async_std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::pin::Pin;
+use std::path::Path;
+use url::{Url, Position, Host};
+use async_std::io::{Read, ReadExt, Write};
+use async_std::fs::File;
+use async_std::future::{Future, timeout};
+use async_std::net::{TcpStream, ToSocketAddrs};
+use async_io::Async;
 use async_uninet::{SocketAddr, Stream};
-use async_httplib::{read_first_line, parse_version, parse_status, read_header_line,
-    write_slice, write_all, write_exact, write_chunks, flush_write};
-use crate::{Method, Version, Response, read_content_length};
+use native_tls::{Certificate, TlsConnector};
+use socket2::{Domain, SockRef, Socket, TcpKeepalive, Type};
+use async_httplib::{write_slice, write_all, write_exact, write_chunks, flush_write};
+use crate::{Method, Version, Status, Response, Connection, Pool, Pooled, LimitBehavior, WebSocketStream, websocket_key, check_websocket_accept, read_line, read_header_fields, HEADER_LIMIT, MAX_HEADER_COUNT, STATUS_LINE_LIMIT, CHUNKLINE_LIMIT, Timing, Duplex, RetryWrite, Tap, read_content_length, is_chunked, TlsStream, tls_handshake, percent_decode, cancellable, poll_once,
+    interleave_addrs, first_ok, redact_header, redact_url, normalize_path,
+    guess_mime_type, sniff_mime_type, encode_base64, split_pem, env_proxy, StreamReader, socks5_handshake, SENSITIVE_HEADERS};
+#[cfg(feature = "http2")]
+use crate::send_h2;
+#[cfg(feature = "compression")]
+use crate::{Codec, Encoder, encode_body};
 
-#[derive(Debug)]
+const CONTINUE_TIMEOUT: u64 = 1000;
+const CONNECT_STAGGER: u64 = 250;
+const CHUNK_SIZE: usize = 1024;
+const COALESCE_LIMIT: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetForm {
+    Origin,
+    Absolute,
+    Asterisk,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooManyRedirects {
+    limit: usize,
+}
+
+impl TooManyRedirects {
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl fmt::Display for TooManyRedirects {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "The request hit the limit of {} redirects.", self.limit)
+    }
+}
+
+impl std::error::Error for TooManyRedirects {}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Transport<'p> {
+    Direct,
+    Pooled(&'p Pool, Option<Duration>),
+}
+
+pub struct DebugExchange<'a> {
+    response: Response<'a>,
+    raw_request: Vec<u8>,
+    raw_response_head: Vec<u8>,
+}
+
+impl<'a> DebugExchange<'a> {
+
+    pub fn response(&self) -> &Response<'a> {
+        &self.response
+    }
+
+    pub fn response_mut(&mut self) -> &mut Response<'a> {
+        &mut self.response
+    }
+
+    pub fn raw_request(&self) -> &Vec<u8> {
+        &self.raw_request
+    }
+
+    pub fn raw_response_head(&self) -> &Vec<u8> {
+        &self.raw_response_head
+    }
+
+    pub fn into_response(self) -> Response<'a> {
+        self.response
+    }
+}
+
+#[derive(Clone)]
 pub struct Request {
     url: Url,
     method: Method,
@@ -18,6 +100,44 @@ pub struct Request {
     headers: HashMap<String, String>,
     relay: Option<String>,
     body_limit: Option<usize>,
+    limit_behavior: LimitBehavior,
+    expect_continue: bool,
+    capture_timing: bool,
+    timing_start: Option<Instant>,
+    timing: Timing,
+    redacted_headers: Vec<String>,
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+    normalize_path: bool,
+    header_limit: Option<usize>,
+    chunkline_limit: Option<usize>,
+    write_retries: usize,
+    chunk_size: usize,
+    target_form: Option<TargetForm>,
+    guess_content_type: bool,
+    tcp_nodelay: bool,
+    tcp_keepalive: Option<Duration>,
+    local_address: Option<std::net::SocketAddr>,
+    max_redirects: Option<usize>,
+    proxy_auth: Option<String>,
+    remote_addr: Option<std::net::SocketAddr>,
+    resolved_addr: Option<std::net::SocketAddr>,
+    max_header_count: usize,
+    status_line_limit: usize,
+    force_body: bool,
+    lenient_parsing: bool,
+    keep_alive: bool,
+    auto_host: bool,
+    connect_timeout: Option<Duration>,
+    tls_timeout: Option<Duration>,
+    ca_certificates: Vec<Certificate>,
+    capture: Option<(Vec<u8>, Vec<u8>)>,
+    connect_deadline: Option<Instant>,
+    signer: Option<Arc<dyn Fn(&Request) -> (String, String) + Send + Sync>>,
+    #[cfg(feature = "compression")]
+    request_compression: Option<Codec>,
+    #[cfg(feature = "compression")]
+    sniff_compression: bool,
 }
 
 impl Request {
@@ -30,6 +150,44 @@ impl Request {
             headers: HashMap::with_hasher(RandomState::new()),
             relay: None,
             body_limit: None,
+            limit_behavior: LimitBehavior::Error,
+            expect_continue: false,
+            capture_timing: false,
+            timing_start: None,
+            timing: Timing::default(),
+            redacted_headers: SENSITIVE_HEADERS.iter().map(|name| name.to_string()).collect(),
+            deadline: None,
+            timeout: None,
+            normalize_path: false,
+            header_limit: Some(HEADER_LIMIT),
+            chunkline_limit: Some(CHUNKLINE_LIMIT),
+            write_retries: 0,
+            chunk_size: CHUNK_SIZE,
+            target_form: None,
+            guess_content_type: false,
+            tcp_nodelay: false,
+            tcp_keepalive: None,
+            local_address: None,
+            max_redirects: None,
+            proxy_auth: None,
+            remote_addr: None,
+            resolved_addr: None,
+            max_header_count: MAX_HEADER_COUNT,
+            status_line_limit: STATUS_LINE_LIMIT,
+            force_body: false,
+            lenient_parsing: false,
+            keep_alive: false,
+            auto_host: true,
+            connect_timeout: None,
+            tls_timeout: None,
+            ca_certificates: Vec::new(),
+            capture: None,
+            connect_deadline: None,
+            signer: None,
+            #[cfg(feature = "compression")]
+            request_compression: None,
+            #[cfg(feature = "compression")]
+            sniff_compression: false,
         }
     }
 
@@ -42,6 +200,73 @@ impl Request {
         Ok(req)
     }
 
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), Error> {
+        let mut reader = bytes;
+        match poll_once(Self::read_from(&mut reader)) { // slice reads never wait
+            Some(Ok(req)) => Ok((req, bytes.len() - reader.len())),
+            Some(Err(e)) if e.kind() == ErrorKind::UnexpectedEof => Err(Error::new(ErrorKind::UnexpectedEof, "The request head is incomplete.")),
+            Some(Err(e)) => Err(e),
+            None => Err(Error::new(ErrorKind::Other, "The request head could not be parsed without waiting.")),
+        }
+    }
+
+    pub async fn read_from<R>(mut stream: R) -> Result<Self, Error>
+        where
+        R: Read + Unpin,
+    {
+        let mut req = Request::default();
+        let mut line = Vec::new();
+        while line.is_empty() { // tolerate leading empty lines
+            line = read_line(&mut stream, Some(req.status_line_limit)).await?;
+        }
+
+        let line = String::from_utf8_lossy(&line).to_string();
+        let invalid = || Error::new(ErrorKind::InvalidData, format!("The request line `{}` is invalid.", line));
+        let parts: Vec<&str> = line.split(' ').collect();
+        let (method, target, version) = match parts.as_slice() {
+            [method, target] => (Method::from_str(method).map_err(|_| invalid())?, *target, Version::Http0_9),
+            [method, target, version] => (Method::from_str(method).map_err(|_| invalid())?, *target, Version::from_str(version).map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        };
+        req.set_method(method);
+        req.set_version(version);
+        if !req.has_version(Version::Http0_9) { // HTTP/0.9 has no headers
+            read_header_fields(&mut stream, &mut req.headers, (req.header_limit, req.max_header_count), false, "request").await?;
+        }
+
+        let host = req.header("Host").cloned().unwrap_or_else(|| "localhost".to_string());
+        if target.starts_with('/') {
+            req.set_url_str(format!("http://{}{}", host, target))?;
+        } else if target == "*" {
+            req.set_url_str(format!("http://{}", host))?;
+            req.set_target_form(TargetForm::Asterisk);
+        } else if req.has_method(Method::Connect) {
+            req.set_url_str(format!("http://{}", target))?;
+        } else {
+            req.set_url_str(target)?;
+            req.set_target_form(TargetForm::Absolute);
+        }
+        Ok(req)
+    }
+
+    pub fn options<U>(url: U) -> Result<Self, Error>
+        where
+        U: Into<String>,
+    {
+        let mut req = Request::parse_url(url)?;
+        req.set_method(Method::Options);
+        Ok(req)
+    }
+
+    pub fn connect<A>(authority: A) -> Result<Self, Error>
+        where
+        A: Into<String>,
+    {
+        let mut req = Request::parse_url(format!("http://{}", authority.into()))?;
+        req.set_method(Method::Connect);
+        Ok(req)
+    }
+
     pub fn url(&self) -> &Url {
         &self.url
     }
@@ -52,7 +277,8 @@ impl Request {
 
     fn host(&self) -> &str {
         match self.url.host_str() {
-            Some(host) => host,
+            Some(_) if self.is_unix() => "localhost",
+            Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
             None => "localhost",
         }
     }
@@ -65,18 +291,108 @@ impl Request {
     }
 
     fn host_with_port(&self) -> String {
-        format!("{}:{}", self.host(), self.port())
+        match self.url.host() {
+            Some(Host::Ipv6(_)) => format!("[{}]:{}", self.host(), self.port()),
+            _ => format!("{}:{}", self.host(), self.port()),
+        }
+    }
+
+    fn host_header(&self) -> String {
+        match (self.url.port(), self.url.host()) { // port is none for the scheme default
+            (Some(_), _) => self.host_with_port(),
+            (None, Some(Host::Ipv6(_))) => format!("[{}]", self.host()),
+            (None, _) => self.host().to_string(),
+        }
+    }
+
+    pub(crate) fn pool_key(&self) -> String {
+        match &self.relay {
+            Some(relay) => format!("{}://{} via {}", self.scheme(), self.host_with_port(), relay),
+            None => format!("{}://{}", self.scheme(), self.host_with_port()),
+        }
     }
 
     fn socket_address(&self) -> String {
+        if let Some(proxy) = self.socks_proxy() {
+            return format!("{}:{}", proxy.host_str().unwrap_or("localhost"), proxy.port().unwrap_or(1080));
+        }
         match &self.relay {
+            Some(relay) if relay.starts_with('/') => format!("unix:{}", relay),
             Some(relay) => relay.to_string(),
-            None => self.host_with_port(),
+            None => match self.unix_path() {
+                Some(path) => format!("unix:{}", path),
+                None => self.host_with_port(),
+            },
+        }
+    }
+
+    fn is_proxied(&self) -> bool {
+        match &self.relay {
+            Some(relay) => !relay.starts_with('/') && !relay.starts_with("unix:") && self.socks_proxy().is_none(),
+            None => false,
+        }
+    }
+
+    fn socks_proxy(&self) -> Option<Url> {
+        match Url::parse(self.relay.as_ref()?) {
+            Ok(url) if url.scheme() == "socks5" || url.scheme() == "socks5h" => Some(url),
+            _ => None,
+        }
+    }
+
+    fn is_unix(&self) -> bool {
+        self.scheme() == "http+unix"
+    }
+
+    fn unix_path(&self) -> Option<String> {
+        if !self.is_unix() {
+            return None;
+        }
+        match self.url.host_str() {
+            Some(host) if !host.is_empty() => Some(percent_decode(host)),
+            _ => {
+                let path = self.url.path();
+                path.find(".sock/").or_else(|| match path.ends_with(".sock") {
+                    true => Some(path.len() - 5),
+                    false => None,
+                }).map(|index| path[..index + 5].to_string())
+            },
+        }
+    }
+
+    pub fn request_target(&self) -> String {
+        if self.has_method(Method::Connect) {
+            return self.uri();
+        }
+        match self.target_form() {
+            TargetForm::Absolute => format!("{}://{}{}", self.scheme(), &self.url[Position::BeforeHost..Position::AfterPort], self.uri()),
+            TargetForm::Origin => self.uri(),
+            TargetForm::Asterisk => "*".to_string(), // server-wide OPTIONS
         }
     }
 
-    fn uri(&self) -> &str {
-        &self.url[Position::BeforePath..]
+    pub(crate) fn uri(&self) -> String {
+        if self.has_method(Method::Connect) {
+            return self.host_with_port();
+        }
+
+        let uri = &self.url[Position::BeforePath..Position::AfterQuery]; // never send the fragment
+        let uri = match self.unix_path() {
+            Some(path) if uri.starts_with(&path) => &uri[path.len()..],
+            _ => uri,
+        };
+        let (path, query) = match uri.find(|c| c == '?' || c == '#') {
+            Some(index) => uri.split_at(index),
+            None => (uri, ""),
+        };
+        let path = match self.normalize_path {
+            true => normalize_path(path),
+            false => path.to_string(),
+        };
+        match path.starts_with('/') {
+            true => format!("{}{}", path, query),
+            false => format!("/{}{}", path, query),
+        }
     }
 
     pub fn method(&self) -> &Method {
@@ -103,6 +419,132 @@ impl Request {
         &self.body_limit
     }
 
+    pub fn limit_behavior(&self) -> LimitBehavior {
+        self.limit_behavior
+    }
+
+    pub fn header_limit(&self) -> &Option<usize> {
+        &self.header_limit
+    }
+
+    pub fn chunkline_limit(&self) -> &Option<usize> {
+        &self.chunkline_limit
+    }
+
+    pub fn max_header_count(&self) -> usize {
+        self.max_header_count
+    }
+
+    pub fn status_line_limit(&self) -> usize {
+        self.status_line_limit
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn request_compression(&self) -> &Option<Codec> {
+        &self.request_compression
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn sniff_compression(&self) -> bool {
+        self.sniff_compression
+    }
+
+    pub fn target_form(&self) -> TargetForm {
+        match (self.target_form, &self.relay) {
+            (Some(form), _) => form,
+            (None, _) if self.is_proxied() && self.scheme() == "http" && !self.has_method(Method::Connect) => TargetForm::Absolute,
+            (None, _) => TargetForm::Origin,
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn write_retries(&self) -> usize {
+        self.write_retries
+    }
+
+    pub fn expect_continue(&self) -> bool {
+        self.expect_continue
+    }
+
+    pub fn capture_timing(&self) -> bool {
+        self.capture_timing
+    }
+
+    pub fn redacted_headers(&self) -> &Vec<String> {
+        &self.redacted_headers
+    }
+
+    pub fn deadline(&self) -> &Option<Instant> {
+        &self.deadline
+    }
+
+    pub fn timeout(&self) -> &Option<Duration> {
+        &self.timeout
+    }
+
+    pub fn connect_timeout(&self) -> &Option<Duration> {
+        &self.connect_timeout
+    }
+
+    pub fn tls_timeout(&self) -> &Option<Duration> {
+        &self.tls_timeout
+    }
+
+    pub fn has_ca_bundle(&self) -> bool {
+        !self.ca_certificates.is_empty()
+    }
+
+    pub fn normalize_path(&self) -> bool {
+        self.normalize_path
+    }
+
+    pub fn auto_host(&self) -> bool {
+        self.auto_host
+    }
+
+    pub fn force_body(&self) -> bool {
+        self.force_body
+    }
+
+    pub fn lenient_parsing(&self) -> bool {
+        self.lenient_parsing
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    pub fn guess_content_type(&self) -> bool {
+        self.guess_content_type
+    }
+
+    pub fn tcp_nodelay(&self) -> bool {
+        self.tcp_nodelay
+    }
+
+    pub fn tcp_keepalive(&self) -> &Option<Duration> {
+        &self.tcp_keepalive
+    }
+
+    pub fn local_address(&self) -> &Option<std::net::SocketAddr> {
+        &self.local_address
+    }
+
+    pub fn resolved_addr(&self) -> &Option<std::net::SocketAddr> {
+        &self.resolved_addr
+    }
+
+    pub fn max_redirects(&self) -> &Option<usize> {
+        &self.max_redirects
+    }
+
+    pub fn has_proxy_auth(&self) -> bool {
+        self.proxy_auth.is_some()
+    }
+
     pub fn headers_mut(&mut self) -> &mut HashMap<String, String> {
         &mut self.headers
     }
@@ -123,6 +565,18 @@ impl Request {
         self.body_limit.is_some()
     }
 
+    pub fn has_header_limit(&self) -> bool {
+        self.header_limit.is_some()
+    }
+
+    pub fn has_chunkline_limit(&self) -> bool {
+        self.chunkline_limit.is_some()
+    }
+
+    pub fn has_deadline(&self) -> bool {
+        self.deadline.is_some() || self.timeout.is_some()
+    }
+
     pub fn set_url(&mut self, value: Url) {
         self.url = value;
     }
@@ -161,193 +615,1277 @@ impl Request {
         self.relay = Some(value.into());
     }
 
+    pub fn set_relay_checked<V: Into<String>>(&mut self, value: V) -> Result<(), Error> {
+        let value = value.into();
+        if !is_valid_relay(&value) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("The relay `{}` is invalid.", value)));
+        }
+        self.relay = Some(value);
+        Ok(())
+    }
+
     pub fn set_body_limit(&mut self, length: usize) {
         self.body_limit = Some(length);
     }
 
-    pub fn remove_header<N: Into<String>>(&mut self, name: N) {
-        self.headers.remove(&name.into());
+    pub fn set_limit_behavior(&mut self, value: LimitBehavior) {
+        self.limit_behavior = value;
     }
 
-    pub fn remove_relay(&mut self) {
-        self.relay = None;
+    pub fn set_header_limit(&mut self, length: usize) {
+        self.header_limit = Some(length);
     }
 
-    pub fn clear_headers(&mut self) {
-        self.headers.clear();
+    pub fn set_chunkline_limit(&mut self, length: usize) {
+        self.chunkline_limit = Some(length);
     }
 
-    pub fn to_proto_string(&self) -> String {
-        let mut output = String::new();
+    pub fn set_target_form(&mut self, value: TargetForm) {
+        self.target_form = Some(value);
+    }
 
-        match self.version {
-            Version::Http0_9 => {
-                output.push_str(&format!("GET {}\r\n", self.uri()));
-            },
-            _ => {
-                output.push_str(&format!("{} {} {}\r\n", self.method(), self.uri(), self.version()));
-                for (name, value) in self.headers.iter() {
-                    output.push_str(&format!("{}: {}\r\n", name, value));
-                }
-                output.push_str("\r\n");
-            },
-        };
+    pub fn set_chunk_size(&mut self, length: usize) -> Result<(), Error> {
+        if length == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "The chunk size must be greater than zero."));
+        }
+        self.chunk_size = length;
+        Ok(())
+    }
 
-        output
+    pub fn set_max_header_count(&mut self, value: usize) {
+        self.max_header_count = value;
     }
 
-    pub async fn send<'a>(&mut self) -> Result<Response<'a>, Error> {
-        self.update_host_header();
+    pub fn set_status_line_limit(&mut self, value: usize) {
+        self.status_line_limit = value;
+    }
 
-        match self.scheme() {
-            "http" => self.send_http(&mut "".as_bytes()).await,
-            "https" => self.send_https(&mut "".as_bytes()).await,
-            s => Err(Error::new(ErrorKind::InvalidInput, format!("The URL scheme `{}` is invalid.", s))),
-        }
+    #[cfg(feature = "compression")]
+    pub fn set_request_compression(&mut self, value: Codec) {
+        self.request_compression = Some(value);
     }
 
-    pub async fn send_stream<'a, R>(&mut self, body: &mut R) -> Result<Response<'a>, Error>
-        where
-        R: Read + Send + Unpin,
-    {
-        self.update_host_header();
-        self.update_body_headers();
-        
-        match self.scheme() {
-            "http" => self.send_http(body).await,
-            "https" => self.send_https(body).await,
-            s => Err(Error::new(ErrorKind::InvalidInput, format!("The URL scheme `{}` is invalid.", s))),
-        }
+    #[cfg(feature = "compression")]
+    pub fn set_sniff_compression(&mut self, value: bool) {
+        self.sniff_compression = value;
     }
 
-    pub async fn send_slice<'a>(&mut self, body: &[u8]) -> Result<Response<'a>, Error> {
-        self.set_header("Content-Length", body.len().to_string());
-        self.send_stream(&mut body.clone()).await
+    pub fn set_write_retries(&mut self, value: usize) {
+        self.write_retries = value;
     }
 
-    pub async fn send_str<'a>(&mut self, body: &str) -> Result<Response<'a>, Error> {
-        self.set_header("Content-Length", body.len().to_string());
-        self.send_stream(&mut body.as_bytes()).await
+    pub fn set_expect_continue(&mut self, value: bool) {
+        self.expect_continue = value;
     }
 
-    #[cfg(feature = "json")]
-    pub async fn send_json<'a>(&mut self, body: &serde_json::Value) -> Result<Response<'a>, Error> {
-        let body = body.to_string();
-        self.set_header("Content-Length", body.len().to_string());
-        self.send_stream(&mut body.as_bytes()).await
+    pub fn set_capture_timing(&mut self, value: bool) {
+        self.capture_timing = value;
     }
 
-    pub async fn send_http<'a, R>(&mut self, body: &mut R) -> Result<Response<'a>, Error>
-        where
-        R: Read + Send + Unpin,
-    {
-        let mut stream = self.build_conn().await?;
-        self.write_request(&mut stream, body).await?;
-        self.build_response(stream).await
+    pub fn set_deadline(&mut self, value: Instant) {
+        self.deadline = Some(value);
     }
 
-    pub async fn send_https<'a, R>(&mut self, body: &mut R) -> Result<Response<'a>, Error>
-        where
-        R: Read + Send + Unpin,
-    {
-        let stream = self.build_conn().await?;
+    pub fn set_timeout(&mut self, value: Duration) {
+        self.timeout = Some(value);
+    }
 
-        let mut stream = match async_native_tls::connect(self.host(), stream).await {
-            Ok(stream) => stream,
-            Err(e) => return Err(Error::new(ErrorKind::Interrupted, e.to_string())),
-        };
+    pub fn set_connect_timeout(&mut self, value: Duration) {
+        self.connect_timeout = Some(value);
+    }
 
-        self.write_request(&mut stream, body).await?;
-        self.build_response(stream).await
+    pub fn set_tls_timeout(&mut self, value: Duration) {
+        self.tls_timeout = Some(value);
     }
 
-    fn update_host_header(&mut self) {
-        if self.version >= Version::Http1_1 && !self.has_header("Host") {
-            self.set_header("Host", self.host_with_port());
-        }
+    pub fn set_ca_bundle<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let data = std::fs::read(path.as_ref())?;
+        self.set_ca_bundle_pem(&data)
     }
 
-    fn update_body_headers(&mut self) {
-        if self.version >= Version::Http0_9 && self.method.has_body() && !self.has_header("Content-Length") {
-            self.set_header("Transfer-Encoding", "chunked");
+    pub fn set_ca_bundle_pem(&mut self, data: &[u8]) -> Result<(), Error> {
+        let mut certificates = Vec::new();
+        for (index, block) in split_pem(data)?.iter().enumerate() {
+            match Certificate::from_pem(block.as_bytes()) {
+                Ok(certificate) => certificates.push(certificate),
+                Err(e) => return Err(Error::new(ErrorKind::InvalidData, format!("The CA bundle certificate #{} is invalid: {}.", index, e))),
+            };
         }
+        self.ca_certificates = certificates;
+        Ok(())
     }
 
-    async fn write_request<S, R>(&self, stream: &mut S, body: &mut R) -> Result<(), Error>
-        where
-        S: Write + Unpin,
-        R: Read + Send + Unpin,
-    {
-        self.write_proto(stream).await?;
-        self.write_body(stream, body).await
+    pub fn set_normalize_path(&mut self, value: bool) {
+        self.normalize_path = value;
     }
 
-    async fn write_proto<S>(&self, stream: &mut S) -> Result<(), Error>
-        where
-        S: Write + Unpin,
-    {
-        write_slice(stream, self.to_string().as_bytes()).await?;
-        flush_write(stream).await
+    pub fn set_auto_host(&mut self, value: bool) {
+        self.auto_host = value;
     }
 
-    async fn write_body<S, R>(&self, stream: &mut S, body: &mut R) -> Result<(), Error>
+    pub fn sign_with<F>(&mut self, signer: F)
         where
-        S: Write + Unpin,
-        R: Read + Send + Unpin,
+        F: Fn(&Request) -> (String, String) + Send + Sync + 'static,
     {
-        if self.has_version(Version::Http0_9) {
-            write_all(stream, body, self.body_limit).await?;
-        } else if self.has_header("Content-Length") { // exact
-            write_exact(stream, body, read_content_length(&self.headers, self.body_limit)?).await?;
-        } else { // chunked
-            write_chunks(stream, body, (Some(1024), self.body_limit)).await?;
-        }
-        flush_write(stream).await
+        self.signer = Some(Arc::new(signer));
     }
 
-    async fn build_conn(&mut self) -> Result<Stream, Error> {
-        let addr = self.socket_address();
+    pub fn set_force_body(&mut self, value: bool) {
+        self.force_body = value;
+    }
 
-        match SocketAddr::from_str(&addr).await {
-            Ok(addr) => Stream::connect(&addr).await,
-            Err(_) => Err(Error::new(ErrorKind::AddrNotAvailable, format!("The address `{}` is invalid.", addr))),
-        }
+    pub fn set_lenient_parsing(&mut self, value: bool) {
+        self.lenient_parsing = value;
     }
 
-    async fn build_response<'a, S>(&mut self, mut stream: S) -> Result<Response<'a>, Error>
-        where
-        S: Read + Send + Unpin + 'a,
-    {
-        let mut res: Response<'a> = Response::default();
-
-        let (mut version, mut status, mut message) = (vec![], vec![], vec![]);
-        read_first_line(&mut stream, (&mut version, &mut status, &mut message), None).await?;
-        res.set_version(parse_version(version)?);
-        res.set_status(parse_status(status)?);
-    
-        loop {
-            let (mut name, mut value) = (vec![], vec![]);
-            read_header_line(&mut stream, (&mut name, &mut value), None).await?;
-            
-            if name.is_empty() {
-                break;
-            }
+    pub fn set_guess_content_type(&mut self, value: bool) {
+        self.guess_content_type = value;
+    }
 
-            res.set_header(
-                match String::from_utf8(name) {
-                    Ok(name) => name,
-                    Err(_) => return Err(Error::new(ErrorKind::InvalidData, format!("The response header `#{}` is invalid.", res.headers().len()))),
-                },
-                match String::from_utf8(value) {
-                    Ok(value) => value,
-                    Err(_) => return Err(Error::new(ErrorKind::InvalidData, format!("The response header `#{}` is invalid.", res.headers().len()))),
+    pub fn set_tcp_nodelay(&mut self, value: bool) {
+        self.tcp_nodelay = value;
+    }
+
+    pub fn set_tcp_keepalive(&mut self, value: Duration) {
+        self.tcp_keepalive = Some(value);
+    }
+
+    pub fn set_local_address(&mut self, value: std::net::SocketAddr) {
+        self.local_address = Some(value);
+    }
+
+    pub fn set_resolved_addr(&mut self, value: std::net::SocketAddr) {
+        self.resolved_addr = Some(value);
+    }
+
+    pub fn set_max_redirects(&mut self, value: usize) {
+        self.max_redirects = Some(value);
+    }
+
+    pub fn set_proxy_auth<U: Into<String>, P: Into<String>>(&mut self, username: U, password: P) {
+        let credentials = format!("{}:{}", username.into(), password.into());
+        self.proxy_auth = Some(format!("Basic {}", encode_base64(credentials.as_bytes())));
+    }
+
+    pub fn use_env_proxy(&mut self) -> Result<(), Error> {
+        let proxy = match env_proxy(self.scheme(), self.host()) {
+            Some(proxy) => proxy,
+            None => return Ok(()),
+        };
+        let url = match Url::parse(&proxy).or_else(|_| Url::parse(&format!("http://{}", proxy))) { // scheme is optional
+            Ok(url) if url.host_str().is_some() => url,
+            _ => return Err(Error::new(ErrorKind::InvalidInput, format!("The proxy `{}` is invalid.", proxy))),
+        };
+
+        match url.scheme() {
+            "socks5" | "socks5h" => self.set_relay(proxy),
+            "http" | "https" => {
+                self.set_relay(format!("{}:{}", url.host_str().unwrap_or("localhost"), url.port_or_known_default().unwrap_or(80)));
+                if !url.username().is_empty() {
+                    self.set_proxy_auth(percent_decode(url.username()), percent_decode(url.password().unwrap_or("")));
+                }
+            },
+            scheme => return Err(Error::new(ErrorKind::InvalidInput, format!("The proxy scheme `{}` is not supported.", scheme))),
+        };
+        Ok(())
+    }
+
+    pub fn set_keep_alive(&mut self, value: bool) {
+        self.keep_alive = value;
+        match (self.version >= Version::Http1_1, value) {
+            (true, true) => self.remove_header("Connection"),
+            (true, false) => self.set_header("Connection", "close"),
+            (false, true) => self.set_header("Connection", "keep-alive"),
+            (false, false) => self.remove_header("Connection"),
+        };
+    }
+
+    pub fn set_range(&mut self, start: u64, end: Option<u64>) {
+        match end {
+            Some(end) => self.set_header("Range", format!("bytes={}-{}", start, end)),
+            None => self.set_header("Range", format!("bytes={}-", start)),
+        };
+    }
+
+    pub fn set_accept<I, M>(&mut self, types: I) -> Result<(), Error>
+        where
+        I: IntoIterator<Item = (M, Option<f32>)>,
+        M: Into<String>,
+    {
+        let mut values = Vec::new();
+        for (mime, quality) in types {
+            let mime = mime.into();
+            match quality {
+                Some(quality) if !(0.0..=1.0).contains(&quality) => {
+                    return Err(Error::new(ErrorKind::InvalidInput, format!("The quality `{}` of `{}` is not between 0 and 1.", quality, mime)));
                 },
-            );
+                Some(quality) => values.push(format!("{};q={}", mime, format_quality(quality))),
+                None => values.push(mime),
+            };
         }
+        self.set_header("Accept", values.join(", "));
+        Ok(())
+    }
 
-        res.set_reader(stream);
+    pub fn accept_json(&mut self) {
+        self.set_header("Accept", "application/json");
+    }
+
+    pub fn set_if_none_match<V: Into<String>>(&mut self, etag: V) {
+        self.set_header("If-None-Match", etag);
+    }
+
+    pub fn set_if_modified_since<V: Into<String>>(&mut self, date: V) {
+        self.set_header("If-Modified-Since", date);
+    }
+
+    pub fn set_redacted_header<N: Into<String>>(&mut self, name: N) {
+        let name = name.into();
+        if !self.redacted_headers.iter().any(|header| header.eq_ignore_ascii_case(&name)) {
+            self.redacted_headers.push(name);
+        }
+    }
+
+    pub fn remove_redacted_header<N: Into<String>>(&mut self, name: N) {
+        let name = name.into();
+        self.redacted_headers.retain(|header| !header.eq_ignore_ascii_case(&name));
+    }
+
+    pub fn remove_header<N: Into<String>>(&mut self, name: N) {
+        self.headers.remove(&name.into());
+    }
+
+    pub fn remove_relay(&mut self) {
+        self.relay = None;
+    }
+
+    pub fn remove_header_limit(&mut self) {
+        self.header_limit = None;
+    }
+
+    pub fn remove_chunkline_limit(&mut self) {
+        self.chunkline_limit = None;
+    }
+
+    pub fn remove_tcp_keepalive(&mut self) {
+        self.tcp_keepalive = None;
+    }
+
+    pub fn remove_local_address(&mut self) {
+        self.local_address = None;
+    }
+
+    pub fn remove_resolved_addr(&mut self) {
+        self.resolved_addr = None;
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn remove_request_compression(&mut self) {
+        self.request_compression = None;
+    }
+
+    pub fn remove_max_redirects(&mut self) {
+        self.max_redirects = None;
+    }
+
+    pub fn remove_proxy_auth(&mut self) {
+        self.proxy_auth = None;
+    }
+
+    pub fn remove_signer(&mut self) {
+        self.signer = None;
+    }
+
+    pub fn remove_connect_timeout(&mut self) {
+        self.connect_timeout = None;
+    }
+
+    pub fn remove_tls_timeout(&mut self) {
+        self.tls_timeout = None;
+    }
+
+    pub fn remove_ca_bundle(&mut self) {
+        self.ca_certificates.clear();
+    }
+
+    pub fn remove_deadline(&mut self) {
+        self.deadline = None;
+        self.timeout = None;
+    }
+
+    pub fn clear_headers(&mut self) {
+        self.headers.clear();
+    }
+
+    pub fn to_proto_string(&self) -> String {
+        let mut output = String::new();
+
+        match self.version {
+            Version::Http0_9 => {
+                output.push_str(&format!("GET {}\r\n", self.uri()));
+            },
+            _ => {
+                output.push_str(&format!("{} {} {}\r\n", self.method(), self.request_target(), self.version()));
+                let proxy = self.is_proxied() && (self.target_form() == TargetForm::Absolute || self.has_method(Method::Connect));
+                for (name, value) in self.sorted_headers() {
+                    output.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                if let Some(auth) = &self.proxy_auth {
+                    if proxy && !self.has_header("Proxy-Authorization") { // injected only for the proxy hop
+                        output.push_str(&format!("Proxy-Authorization: {}\r\n", auth));
+                    }
+                }
+                output.push_str("\r\n");
+            },
+        };
+
+        output
+    }
+
+    pub fn proto_bytes(&self) -> Vec<u8> {
+        self.to_proto_string().into_bytes()
+    }
+
+    pub async fn dry_run(&self, body: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut req = self.clone(); // prepared exactly like send_slice, the original stays untouched
+        req.set_header("Content-Length", body.len().to_string());
+        req.update_content_type(sniff_mime_type(body));
+
+        #[cfg(feature = "compression")]
+        if let Some(codec) = req.request_compression {
+            req.remove_header("Content-Length");
+            req.set_header("Content-Encoding", codec.to_string());
+            req.update_headers(true);
+            return req.serialize(&mut Encoder::new(&mut body.clone(), codec)).await;
+        }
+
+        req.update_headers(true);
+        req.serialize(&mut body.clone()).await
+    }
+
+    async fn serialize<R>(mut self, body: &mut R) -> Result<Vec<u8>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        self.update_connection_header();
+        self.sign();
+        self.check_body_framing()?;
+
+        let mut output = Vec::new();
+        self.write_proto(&mut output).await?;
+        self.write_body(&mut output, body).await?;
+        Ok(output)
+    }
+
+    pub async fn send<'a>(&mut self) -> Result<Response<'a>, Error> {
+        self.send_via(Transport::Direct, self.deadline_at()).await
+    }
+
+    pub(crate) async fn send_via<'a>(&mut self, transport: Transport<'_>, deadline: Option<Instant>) -> Result<Response<'a>, Error> {
+        self.update_headers(false);
+        self.dispatch(&mut "".as_bytes(), transport, deadline).await
+    }
+
+    pub async fn send_debug<'a>(&mut self) -> Result<DebugExchange<'a>, Error> {
+        self.capture = Some((Vec::new(), Vec::new())); // only allocated here
+        let res = self.send().await;
+        let (raw_request, raw_response_head) = self.capture.take().unwrap_or_default();
+        Ok(DebugExchange {
+            response: res?,
+            raw_request,
+            raw_response_head,
+        })
+    }
+
+    pub async fn upgrade_websocket<'a>(&self) -> Result<WebSocketStream<'a>, Error> {
+        let mut req = self.clone(); // the scheme rewrite and handshake headers stay local
+        let scheme = match req.scheme() {
+            "ws" => Some("http"),
+            "wss" => Some("https"),
+            _ => None,
+        };
+        if let Some(scheme) = scheme {
+            let _ = req.url.set_scheme(scheme);
+        }
+
+        let key = match req.header("Sec-WebSocket-Key") {
+            Some(key) => key.clone(),
+            None => websocket_key(),
+        };
+        req.set_header("Upgrade", "websocket");
+        req.set_header("Connection", "Upgrade");
+        req.set_header("Sec-WebSocket-Key", &key);
+        req.set_header("Sec-WebSocket-Version", "13");
+
+        let res = req.send().await?;
+        if !res.has_status(Status::SwitchingProtocols) {
+            return Err(Error::new(ErrorKind::ConnectionRefused, format!("The server refused the WebSocket upgrade with status `{}`.", res.status())));
+        }
+        check_websocket_accept(res.headers(), &key)?;
+        match res.into_upgraded() {
+            Some(stream) => Ok(WebSocketStream::new(stream)),
+            None => Err(Error::new(ErrorKind::InvalidData, "The response did not hand over the upgraded stream.")),
+        }
+    }
+
+    pub async fn send_until<'a, C>(&mut self, cancel: C) -> Result<Response<'a>, Error>
+        where
+        C: Future<Output = ()>,
+    {
+        cancellable(self.send(), cancel).await
+    }
+
+    pub async fn send_stream<'a, R>(&mut self, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        self.send_stream_via(body, Transport::Direct, self.deadline_at()).await
+    }
+
+    pub(crate) async fn send_stream_via<'a, R>(&mut self, body: &mut R, transport: Transport<'_>, deadline: Option<Instant>) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        #[cfg(feature = "compression")]
+        if let Some(codec) = self.request_compression {
+            self.remove_header("Content-Length"); // compressed size is unknown
+            self.set_header("Content-Encoding", codec.to_string());
+            self.update_headers(true);
+            return self.dispatch(&mut Encoder::new(body, codec), transport, deadline).await;
+        }
+
+        self.update_headers(true);
+        self.dispatch(body, transport, deadline).await
+    }
+
+    pub async fn send_slice<'a>(&mut self, body: &[u8]) -> Result<Response<'a>, Error> {
+        self.set_header("Content-Length", body.len().to_string());
+        self.update_content_type(sniff_mime_type(body));
+        self.send_stream(&mut body.clone()).await
+    }
+
+    pub async fn send_file<'a, P: AsRef<Path>>(&mut self, path: P) -> Result<Response<'a>, Error> {
+        let file = File::open(path.as_ref()).await?;
+        let length = file.metadata().await?.len();
+        self.update_content_type(guess_mime_type(path.as_ref()));
+        self.send_sized_stream(file, length).await // trust stat length
+    }
+
+    pub async fn send_sized_stream<'a, R>(&mut self, body: R, length: u64) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        self.set_header("Content-Length", length.to_string());
+        self.send_stream(&mut body.take(length)).await
+    }
+
+    pub async fn send_byte_stream<'a, S>(&mut self, stream: S, length: Option<u64>) -> Result<Response<'a>, Error>
+        where
+        S: async_std::stream::Stream<Item = Result<Vec<u8>, Error>> + Send + Unpin,
+    {
+        match length {
+            Some(length) => self.send_sized_stream(StreamReader::new(stream), length).await,
+            None => {
+                self.remove_header("Content-Length"); // framed as chunked
+                self.send_stream(&mut StreamReader::new(stream)).await
+            },
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    pub async fn send_gzip<'a>(&mut self, body: &[u8]) -> Result<Response<'a>, Error> {
+        let body = encode_body(Codec::Gzip, body)?;
+        self.set_header("Content-Encoding", Codec::Gzip.to_string());
+        self.set_header("Content-Length", body.len().to_string());
+        self.update_headers(true);
+        self.dispatch(&mut body.as_slice(), Transport::Direct, self.deadline_at()).await
+    }
+
+    pub async fn send_str<'a>(&mut self, body: &str) -> Result<Response<'a>, Error> {
+        self.set_header("Content-Length", body.len().to_string());
+        self.send_stream(&mut body.as_bytes()).await
+    }
+
+    #[cfg(feature = "json")]
+    pub async fn send_json<'a>(&mut self, body: &serde_json::Value) -> Result<Response<'a>, Error> {
+        let body = body.to_string();
+        self.set_header("Content-Length", body.len().to_string());
+        self.send_stream(&mut body.as_bytes()).await
+    }
+
+    pub async fn send_over<'a, S, R>(&mut self, stream: S, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        S: Read + Write + Send + Unpin + 'a,
+        R: Read + Send + Unpin,
+    {
+        self.update_headers(true);
+        self.exchange(stream, body).await
+    }
+
+    pub async fn send_http<'a, R>(&mut self, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        let stream = self.build_conn().await?;
+        self.exchange(stream, body).await
+    }
+
+    pub async fn send_https<'a, R>(&mut self, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        let mut stream = self.build_conn().await?;
+        if self.is_proxied() {
+            connect_within(self.connect_deadline, self.open_tunnel(&mut stream)).await?;
+        }
+
+        let protocols: &[&str] = match cfg!(feature = "http2") && !self.has_header("Upgrade") {
+            true => &["h2", "http/1.1"],
+            false => &["http/1.1"], // upgrades only exist in HTTP/1.1
+        };
+
+        let stream = self.tls_connect(protocols, stream).await?;
+        if let Some(elapsed) = self.elapsed() {
+            self.timing.set_tls(elapsed);
+        }
+
+        let protocol = stream.negotiated_alpn();
+
+        #[cfg(feature = "http2")]
+        let mut res = match protocol.as_deref() {
+            Some("h2") => self.exchange_h2(stream, body).await?,
+            _ => self.exchange(stream, body).await?,
+        };
+        #[cfg(not(feature = "http2"))]
+        let mut res = self.exchange(stream, body).await?;
+
+        if let Some(protocol) = protocol {
+            res.set_negotiated_protocol(protocol);
+        }
+        Ok(res)
+    }
+
+    #[cfg(feature = "http2")]
+    async fn exchange_h2<'a, S, R>(&mut self, stream: S, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        S: Read + Write + Send + Unpin + 'static,
+        R: Read + Send + Unpin,
+    {
+        self.sign();
+        let mut res = send_h2(self, stream, body).await?;
+        self.configure_response(&mut res);
+
+        if let Some(elapsed) = self.elapsed() {
+            self.timing.set_total(elapsed);
+            res.set_timing(self.timing.clone());
+        }
+
+        Ok(res)
+    }
+
+    pub(crate) async fn open_conn(&mut self) -> Result<Box<dyn Duplex>, Error> {
+        let mut stream = self.build_conn().await?;
+        if self.is_proxied() && self.scheme() == "https" {
+            connect_within(self.connect_deadline, self.open_tunnel(&mut stream)).await?;
+        }
+
+        match self.scheme() {
+            "http" | "http+unix" => Ok(Box::new(stream)),
+            "https" => Ok(Box::new(self.tls_connect(&["http/1.1"], stream).await?)),
+            s => Err(Error::new(ErrorKind::InvalidInput, format!("The URL scheme `{}` is invalid.", s))),
+        }
+    }
+
+    async fn tls_connect(&self, protocols: &[&str], stream: Stream) -> Result<TlsStream<Stream>, Error> {
+        let mut builder = TlsConnector::builder();
+        for certificate in self.ca_certificates.iter() {
+            builder.add_root_certificate(certificate.clone());
+        }
+        builder.request_alpns(protocols);
+        let connector = match builder.build() {
+            Ok(connector) => connector,
+            Err(e) => return Err(Error::new(ErrorKind::Other, format!("The TLS connector could not be built: {}.", e))),
+        };
+
+        connect_within(self.connect_deadline, async {
+            let handshake = tls_handshake(&connector, self.host(), stream);
+            match self.tls_timeout {
+                Some(duration) => match timeout(duration, handshake).await {
+                    Ok(result) => result,
+                    Err(_) => Err(Error::new(ErrorKind::TimedOut, "The TLS handshake timeout has been exceeded.")),
+                },
+                None => handshake.await,
+            }
+        }).await
+    }
+
+    async fn open_tunnel<S>(&self, stream: &mut S) -> Result<(), Error>
+        where
+        S: Read + Write + Unpin,
+    {
+        let authority = self.host_with_port();
+        let mut head = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+        if let Some(auth) = &self.proxy_auth {
+            head.push_str(&format!("Proxy-Authorization: {}\r\n", auth));
+        }
+        head.push_str("\r\n");
+        write_slice(stream, head.as_bytes()).await?;
+        flush_write(stream).await?;
+
+        let mut res: Response = Response::default();
+        res.read_final_head(stream, self.head_limits(), self.lenient_parsing).await?;
+        match (*res.status() as u16) / 100 {
+            2 => Ok(()),
+            _ => Err(Error::new(ErrorKind::ConnectionRefused, format!("The proxy refused to open a tunnel to `{}` with status `{}`.", authority, res.status()))),
+        }
+    }
+
+    pub(crate) fn remote_addr(&self) -> &Option<std::net::SocketAddr> {
+        &self.remote_addr
+    }
+
+    pub(crate) async fn exchange<'a, S, R>(&mut self, mut stream: S, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        S: Read + Write + Send + Unpin + 'a,
+        R: Read + Send + Unpin,
+    {
+        let mut res = self.transmit(&mut stream, body).await?;
+        self.attach(&mut res, stream);
         Ok(res)
     }
+
+    pub(crate) async fn transmit<'a, S, R>(&mut self, stream: &mut S, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        S: Read + Write + Unpin,
+        R: Read + Send + Unpin,
+    {
+        self.sign();
+        if let Some((mut written, mut read)) = self.capture.take() {
+            written.clear(); // keep the last hop only
+            read.clear();
+            let res = self.transmit_plain(&mut Tap::new(stream, &mut written, &mut read), body).await;
+            self.capture = Some((written, read));
+            return res;
+        }
+        self.transmit_plain(stream, body).await
+    }
+
+    async fn transmit_plain<'a, S, R>(&mut self, stream: &mut S, body: &mut R) -> Result<Response<'a>, Error>
+        where
+        S: Read + Write + Unpin,
+        R: Read + Send + Unpin,
+    {
+        match self.write_request(stream, body).await? {
+            Some(res) => Ok(res), // rejected before sending body
+            None => self.read_response(stream).await,
+        }
+    }
+
+    pub(crate) fn attach<'a, S>(&mut self, res: &mut Response<'a>, stream: S)
+        where
+        S: Read + Write + Send + Unpin + 'a,
+    {
+        match res.is_switching() {
+            true => res.set_upgraded(stream),
+            false => res.set_reader(stream),
+        };
+
+        if let Some(elapsed) = self.elapsed() {
+            self.timing.set_total(elapsed);
+            res.set_timing(self.timing.clone());
+        }
+    }
+
+    fn sign(&mut self) {
+        if let Some(signer) = self.signer.clone() { // runs last, after all automatic headers
+            let (name, value) = signer(self);
+            self.set_header(name, value);
+        }
+    }
+
+    pub(crate) fn is_idempotent(&self) -> bool {
+        match self.method {
+            Method::Get | Method::Head | Method::Put | Method::Delete | Method::Options | Method::Trace => true,
+            _ => false,
+        }
+    }
+
+    fn elapsed(&self) -> Option<Duration> {
+        self.timing_start.map(|start| start.elapsed())
+    }
+
+    async fn dispatch<'a, R>(&mut self, body: &mut R, transport: Transport<'_>, deadline: Option<Instant>) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        #[cfg(feature = "logging")]
+        let start = Instant::now();
+
+        let original = (self.url.clone(), self.method.clone(), self.headers.clone());
+        if let Transport::Direct = transport { // pooled connections stay open
+            self.update_connection_header();
+        }
+        let res = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) => match timeout(remaining, self.follow(body, transport)).await {
+                    Ok(res) => res,
+                    Err(_) => Err(Error::new(ErrorKind::TimedOut, "The request deadline has been exceeded.")),
+                },
+                None => Err(Error::new(ErrorKind::TimedOut, "The request deadline has been exceeded.")),
+            },
+            None => self.follow(body, transport).await,
+        };
+        let (url, method, headers) = original; // undo redirects
+        self.url = url;
+        self.method = method;
+        self.headers = headers;
+
+        #[cfg(feature = "logging")]
+        match &res {
+            Ok(res) => log::debug!("{} {} {} ({:?})", self.method, redact_url(&self.url), res.status(), start.elapsed()),
+            Err(e) => log::debug!("{} {} failed: {} ({:?})", self.method, redact_url(&self.url), e, start.elapsed()),
+        };
+
+        res
+    }
+
+    async fn follow<'a, R>(&mut self, body: &mut R, transport: Transport<'_>) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        let mut history = Vec::new();
+        let mut res = self.route(body, transport).await?;
+
+        while let Some(location) = self.redirect_location(&res) {
+            let limit = self.max_redirects.unwrap_or(0);
+            if history.len() >= limit {
+                return Err(Error::new(ErrorKind::Other, TooManyRedirects { limit }));
+            }
+            history.push(self.url.clone());
+            self.redirect(res.status(), location);
+            res = self.route(&mut "".as_bytes(), transport).await?;
+        }
+
+        res.set_final_url(self.url.clone());
+        res.set_redirect_history(history);
+        Ok(res)
+    }
+
+    fn redirect_location(&self, res: &Response) -> Option<Url> {
+        self.max_redirects?;
+        match res.status() {
+            Status::MovedPermanently | Status::Found | Status::SeeOther => {},
+            Status::TemporaryRedirect | Status::PermanentRedirect if !self.has_body() => {},
+            _ => return None,
+        };
+        self.url.join(res.header("Location")?.trim()).ok()
+    }
+
+    fn redirect(&mut self, status: &Status, location: Url) {
+        let rewrite = match status {
+            Status::SeeOther => !self.has_method(Method::Head),
+            Status::MovedPermanently | Status::Found => self.has_method(Method::Post),
+            _ => false,
+        };
+        if rewrite || self.has_body() { // body cannot be replayed
+            self.method = Method::Get;
+            for name in ["Content-Length", "Content-Type", "Transfer-Encoding", "Expect"].iter() {
+                self.remove_header(*name);
+            }
+        }
+        if location.origin() != self.url.origin() {
+            for name in ["Authorization", "Cookie"].iter() {
+                self.remove_header(*name);
+            }
+        }
+        if self.auto_host {
+            self.remove_header("Host");
+        }
+        self.url = location;
+        self.update_headers(false);
+    }
+
+    fn has_body(&self) -> bool {
+        self.has_header("Content-Length") || self.has_header("Transfer-Encoding")
+    }
+
+    async fn route<'a, R>(&mut self, body: &mut R, transport: Transport<'_>) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        let mut res = match (self.scheme(), transport) {
+            (_, Transport::Pooled(pool, idle_timeout)) => self.send_pooled(body, pool, idle_timeout).await?,
+            ("http", _) | ("http+unix", _) => self.send_http(body).await?,
+            ("https", _) => self.send_https(body).await?,
+            (s, _) => return Err(Error::new(ErrorKind::InvalidInput, format!("The URL scheme `{}` is invalid.", s))),
+        };
+        if let Some(addr) = self.remote_addr {
+            res.set_remote_addr(addr);
+        }
+        Ok(res)
+    }
+
+    async fn send_pooled<'a, R>(&mut self, body: &mut R, pool: &Pool, idle_timeout: Option<Duration>) -> Result<Response<'a>, Error>
+        where
+        R: Read + Send + Unpin,
+    {
+        let key = self.pool_key();
+        let mut conn = match pool.checkout(&key, idle_timeout) {
+            Some(conn) => conn,
+            None => Connection::open(self).await?,
+        };
+
+        let replayable = !self.has_body();
+        let mut res = conn.dispatch(self, body, replayable).await?;
+        self.attach(&mut res, Pooled::new(conn, pool.clone(), key));
+        Ok(res)
+    }
+
+    pub(crate) fn deadline_at(&self) -> Option<Instant> {
+        let timeout = self.timeout.map(|timeout| Instant::now() + timeout);
+        match (self.deadline, timeout) {
+            (Some(deadline), Some(timeout)) => Some(deadline.min(timeout)),
+            (deadline, timeout) => deadline.or(timeout),
+        }
+    }
+
+    pub(crate) fn update_headers(&mut self, body: bool) {
+        self.update_host_header();
+        self.update_encoding_headers();
+        if is_chunked(&self.headers) {
+            self.remove_header("Content-Length"); // explicit chunked framing wins on every send path
+        }
+        if body {
+            self.update_body_headers();
+        }
+    }
+
+    fn update_host_header(&mut self) {
+        if self.auto_host && self.version >= Version::Http1_1 && !self.has_header("Host") {
+            self.set_header("Host", self.host_header());
+        }
+    }
+
+    fn update_connection_header(&mut self) {
+        if self.version >= Version::Http1_1 && !self.keep_alive && !self.has_method(Method::Connect) && !self.has_header("Connection") && !self.has_header("Upgrade") {
+            self.set_header("Connection", "close"); // one-shot, the connection is not reused
+        }
+    }
+
+    fn update_encoding_headers(&mut self) {
+        #[cfg(feature = "compression")]
+        if self.version >= Version::Http1_0 && !self.has_header("Accept-Encoding") {
+            self.set_header("Accept-Encoding", "gzip, deflate");
+        }
+    }
+
+    fn update_body_headers(&mut self) {
+        if self.has_method(Method::Trace) || self.has_method(Method::Connect) {
+            self.remove_header("Content-Length");
+            self.remove_header("Transfer-Encoding");
+            return;
+        }
+        let has_body = self.method.has_body() || self.force_body;
+        if self.version >= Version::Http0_9 && has_body && !self.has_header("Content-Length") && !self.has_header("Transfer-Encoding") {
+            self.set_header("Transfer-Encoding", "chunked");
+        }
+        if self.version >= Version::Http1_1 && has_body && self.expect_continue {
+            self.set_header("Expect", "100-continue");
+        }
+    }
+
+    fn update_content_type(&mut self, mime: Option<&str>) {
+        if self.guess_content_type && !self.has_header("Content-Type") {
+            self.set_header("Content-Type", mime.unwrap_or("application/octet-stream"));
+        }
+    }
+
+    pub(crate) fn has_connection_token(&self, token: &str) -> bool {
+        self.headers.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Connection"))
+            .any(|(_, value)| value.split(',').any(|item| item.trim().eq_ignore_ascii_case(token)))
+    }
+
+    fn expects_continue(&self) -> bool {
+        match self.header("Expect") {
+            Some(expect) => expect.eq_ignore_ascii_case("100-continue"),
+            None => false,
+        }
+    }
+
+    async fn write_request<'a, S, R>(&mut self, stream: &mut S, body: &mut R) -> Result<Option<Response<'a>>, Error>
+        where
+        S: Read + Write + Unpin,
+        R: Read + Send + Unpin,
+    {
+        self.check_body_framing()?;
+        let retries = self.allowed_write_retries();
+        if let Some(length) = self.coalesced_length() {
+            self.write_coalesced(&mut RetryWrite::new(stream, retries), body, length).await?;
+            return Ok(None);
+        }
+        self.write_proto(&mut RetryWrite::new(stream, retries)).await?;
+
+        if self.expects_continue() {
+            if let Some(res) = self.read_continue(stream).await? {
+                return Ok(Some(res)); // rejected before sending body
+            }
+        }
+
+        self.write_body(&mut RetryWrite::new(stream, retries), body).await?;
+        Ok(None)
+    }
+
+    fn check_body_framing(&self) -> Result<(), Error> {
+        if !self.has_header("Transfer-Encoding") || self.has_method(Method::Trace) || self.has_method(Method::Connect) {
+            Ok(())
+        } else if !is_chunked(&self.headers) {
+            Err(Error::new(ErrorKind::InvalidInput, "The request `Transfer-Encoding` must end with `chunked`."))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn allowed_write_retries(&self) -> usize {
+        match self.is_idempotent() {
+            true => self.write_retries,
+            false => 0,
+        }
+    }
+
+    fn coalesced_length(&self) -> Option<usize> {
+        if self.expects_continue() || self.has_version(Version::Http0_9) || self.has_method(Method::Trace) || self.has_method(Method::Connect) || self.has_header("Transfer-Encoding") {
+            return None;
+        }
+        match self.header("Content-Length") {
+            Some(_) => match read_content_length(&self.headers, self.body_limit) {
+                Ok(length) if length <= COALESCE_LIMIT => Some(length),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    async fn write_coalesced<S, R>(&self, stream: &mut S, body: &mut R, length: usize) -> Result<(), Error>
+        where
+        S: Write + Unpin,
+        R: Read + Send + Unpin,
+    {
+        #[cfg(feature = "logging")]
+        self.log_proto();
+
+        let mut data = self.to_string().into_bytes();
+        let size = body.take(length as u64).read_to_end(&mut data).await?;
+        if size < length {
+            return Err(Error::new(ErrorKind::UnexpectedEof, format!("The request body is shorter than the `Content-Length` of {} bytes.", length)));
+        }
+        write_slice(stream, &data).await?; // headers and body in one write
+        flush_write(stream).await
+    }
+
+    async fn write_proto<S>(&self, stream: &mut S) -> Result<(), Error>
+        where
+        S: Write + Unpin,
+    {
+        #[cfg(feature = "logging")]
+        self.log_proto();
+
+        write_slice(stream, self.to_string().as_bytes()).await?;
+        flush_write(stream).await
+    }
+
+    fn sorted_headers(&self) -> Vec<(&String, &String)> {
+        let mut headers: Vec<(&String, &String)> = self.headers.iter().collect();
+        headers.sort_by_key(|(name, _)| (!name.eq_ignore_ascii_case("Host"), name.to_ascii_lowercase())); // Host first, then by name
+        headers
+    }
+
+    #[cfg(feature = "logging")]
+    fn log_proto(&self) {
+        log::trace!("> {} {} {}", self.method, self.request_target(), self.version);
+        for (name, value) in self.sorted_headers() {
+            log::trace!("> {}: {}", name, redact_header(&self.redacted_headers, name, value));
+        }
+    }
+
+    async fn write_body<S, R>(&self, stream: &mut S, body: &mut R) -> Result<(), Error>
+        where
+        S: Write + Unpin,
+        R: Read + Send + Unpin,
+    {
+        if self.has_method(Method::Trace) || self.has_method(Method::Connect) { // no body
+            return Ok(());
+        } else if self.has_version(Version::Http0_9) {
+            write_all(stream, body, self.body_limit).await?;
+        } else if is_chunked(&self.headers) { // chunked
+            write_chunks(stream, body, (Some(self.chunk_size), self.body_limit)).await?;
+        } else if self.has_header("Content-Length") { // exact
+            write_exact(stream, body, read_content_length(&self.headers, self.body_limit)?).await?;
+        }
+        flush_write(stream).await
+    }
+
+    async fn build_conn(&mut self) -> Result<Stream, Error> {
+        self.connect_deadline = self.connect_timeout.map(|timeout| Instant::now() + timeout);
+        connect_within(self.connect_deadline, self.establish_conn()).await
+    }
+
+    async fn establish_conn(&mut self) -> Result<Stream, Error> {
+        let addr = self.socket_address();
+        self.remote_addr = None;
+        self.timing = Timing::default();
+        self.timing_start = match self.capture_timing {
+            true => Some(Instant::now()),
+            false => None,
+        };
+
+        let mut stream = if !addr.starts_with("unix:") { // TCP relays are dialed like origins, from the local address too
+            let addrs = match self.resolved_addr {
+                Some(resolved) if self.relay.is_none() => vec![resolved], // skip DNS
+                _ => match addr.to_socket_addrs().await {
+                    Ok(addrs) => {
+                        let addrs: Vec<std::net::SocketAddr> = addrs.collect();
+                        let usable: Vec<std::net::SocketAddr> = addrs.iter().copied().filter(|addr| self.can_bind(addr)).collect();
+                        match self.local_address {
+                            Some(local) if usable.is_empty() && !addrs.is_empty() => return Err(Error::new(ErrorKind::AddrNotAvailable, format!("The local address `{}` does not match the address family of `{}`.", local.ip(), addr))),
+                            _ => interleave_addrs(usable),
+                        }
+                    },
+                    Err(_) => return Err(Error::new(ErrorKind::AddrNotAvailable, format!("The address `{}` is invalid.", addr))),
+                },
+            };
+            if let Some(elapsed) = self.elapsed() {
+                self.timing.set_dns(elapsed);
+            }
+            self.race_conns(addrs).await?
+        } else {
+            match SocketAddr::from_str(&addr).await {
+                Ok(addr) => Stream::connect(&addr).await?,
+                Err(_) => return Err(Error::new(ErrorKind::AddrNotAvailable, format!("The address `{}` is invalid.", addr))),
+            }
+        };
+
+        self.configure_stream(&stream)?;
+        if let Stream::Tcp(stream) = &stream {
+            self.remote_addr = stream.peer_addr().ok();
+        }
+
+        if let Some(proxy) = self.socks_proxy() {
+            let auth = match proxy.username() {
+                "" => None,
+                username => Some((percent_decode(username), percent_decode(proxy.password().unwrap_or("")))),
+            };
+            let host = match proxy.scheme() {
+                "socks5h" => self.host().to_string(), // resolved by the proxy
+                _ => match (self.host(), self.port()).to_socket_addrs().await?.next() {
+                    Some(addr) => addr.ip().to_string(),
+                    None => return Err(Error::new(ErrorKind::AddrNotAvailable, format!("The address `{}` could not be resolved.", self.host()))),
+                },
+            };
+            socks5_handshake(&mut stream, &host, self.port(), auth).await?;
+        }
+
+        if let Some(elapsed) = self.elapsed() {
+            self.timing.set_connect(elapsed);
+        }
+        Ok(stream)
+    }
+
+    fn configure_stream(&self, stream: &Stream) -> Result<(), Error> {
+        if let Stream::Tcp(stream) = stream { // unix sockets have no TCP options
+            stream.set_nodelay(self.tcp_nodelay)?;
+            if let Some(time) = self.tcp_keepalive {
+                SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn can_bind(&self, addr: &std::net::SocketAddr) -> bool {
+        match self.local_address {
+            Some(local) => local.is_ipv4() == addr.is_ipv4(),
+            None => true,
+        }
+    }
+
+    async fn race_conns(&self, addrs: Vec<std::net::SocketAddr>) -> Result<Stream, Error> {
+        let local = self.local_address;
+        let attempts = addrs.into_iter().map(|addr| {
+            Box::pin(async move {
+                match local {
+                    Some(local) => connect_from(local, addr).await,
+                    None => match SocketAddr::from_str(&addr.to_string()).await {
+                        Ok(addr) => Stream::connect(&addr).await,
+                        Err(_) => Err(Error::new(ErrorKind::AddrNotAvailable, format!("The address `{}` is invalid.", addr))),
+                    },
+                }
+            }) as Pin<Box<dyn Future<Output = Result<Stream, Error>> + Send>>
+        }).collect();
+
+        match first_ok(attempts, Duration::from_millis(CONNECT_STAGGER)).await {
+            Ok(stream) => Ok(stream),
+            Err(e) => Err(Error::new(e.kind(), crate::Error::UnableToConnect(e))), // kind of the last failed attempt
+        }
+    }
+
+    fn configure_response(&self, res: &mut Response) {
+        if let Some(limit) = self.chunkline_limit {
+            res.set_chunkline_limit(limit);
+        }
+        match self.header_limit {
+            Some(limit) => res.set_header_limit(limit),
+            None => res.remove_header_limit(),
+        };
+        res.set_max_header_count(self.max_header_count);
+        if let Some(limit) = self.body_limit {
+            res.set_body_limit(limit);
+        }
+        res.set_limit_behavior(self.limit_behavior);
+        #[cfg(feature = "compression")]
+        res.set_sniff_compression(self.sniff_compression);
+    }
+
+    fn new_response<'a>(&self) -> Response<'a> {
+        let mut res = Response::default();
+        res.set_request_method(self.method.clone());
+        self.configure_response(&mut res);
+        res
+    }
+
+    async fn read_response<'a, S>(&mut self, stream: &mut S) -> Result<Response<'a>, Error>
+        where
+        S: Read + Unpin,
+    {
+        let mut res: Response<'a> = self.new_response();
+        if self.has_version(Version::Http0_9) { // raw body without status line and headers
+            res.set_version(Version::Http0_9);
+            return Ok(res);
+        }
+
+        let mut byte = [0u8; 1];
+        let size = stream.read(&mut byte).await?;
+        self.record_first_byte();
+        res.read_final_head(&mut (&byte[..size]).chain(&mut *stream), self.head_limits(), self.lenient_parsing).await?;
+        #[cfg(feature = "logging")]
+        self.log_head(&res);
+        Ok(res)
+    }
+
+    async fn read_continue<'a, S>(&mut self, stream: &mut S) -> Result<Option<Response<'a>>, Error>
+        where
+        S: Read + Unpin,
+    {
+        let mut res: Response<'a> = self.new_response();
+
+        let mut byte = [0u8; 1];
+        let size = match timeout(Duration::from_millis(CONTINUE_TIMEOUT), stream.read(&mut byte)).await {
+            Ok(size) => size?,
+            Err(_) => return Ok(None), // server does not support 100-continue
+        };
+        self.record_first_byte();
+        res.read_head(&mut (&byte[..size]).chain(&mut *stream), self.head_limits(), self.lenient_parsing).await?; // once it starts, the head is read without a deadline
+        #[cfg(feature = "logging")]
+        self.log_head(&res);
+
+        match res.is_interim() {
+            true => Ok(None),
+            false => Ok(Some(res)),
+        }
+    }
+
+    fn head_limits(&self) -> (usize, Option<usize>, usize) {
+        (self.status_line_limit, self.header_limit, self.max_header_count)
+    }
+
+    fn record_first_byte(&mut self) {
+        if let Some(elapsed) = self.elapsed() {
+            if self.timing.first_byte().is_none() {
+                self.timing.set_first_byte(elapsed);
+            }
+        }
+    }
+
+    #[cfg(feature = "logging")]
+    fn log_head(&self, res: &Response) {
+        log::trace!("< {} {} {}", res.version(), res.status(), res.status().reason());
+        for (name, value) in res.headers().iter() {
+            log::trace!("< {}: {}", name, redact_header(&self.redacted_headers, name, value));
+        }
+    }
+}
+
+async fn connect_within<T, F>(deadline: Option<Instant>, future: F) -> Result<T, Error>
+    where
+    F: Future<Output = Result<T, Error>>,
+{
+    match deadline {
+        Some(deadline) => match timeout(deadline.saturating_duration_since(Instant::now()), future).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::new(ErrorKind::TimedOut, crate::Error::ConnectTimedOut)),
+        },
+        None => future.await,
+    }
+}
+
+async fn connect_from(local: std::net::SocketAddr, addr: std::net::SocketAddr) -> Result<Stream, Error> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    socket.bind(&local.into())?;
+    socket.set_nonblocking(true)?;
+    match socket.connect(&addr.into()) {
+        Ok(_) => {},
+        Err(e) if is_connecting(&e) => {},
+        Err(e) => return Err(e),
+    };
+
+    let stream = Async::new(std::net::TcpStream::from(socket))?;
+    stream.writable().await?; // registered with the reactor, so dropping the future cancels the connect
+    if let Some(e) = stream.get_ref().take_error()? {
+        return Err(e);
+    }
+    Ok(Stream::Tcp(TcpStream::from(stream.into_inner()?)))
+}
+
+fn is_connecting(e: &Error) -> bool {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(libc::EINPROGRESS) {
+        return true;
+    }
+    e.kind() == ErrorKind::WouldBlock
+}
+
+impl fmt::Debug for Request {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let headers: HashMap<&String, &str> = self.headers.iter()
+            .map(|(name, value)| (name, redact_header(&self.redacted_headers, name, value)))
+            .collect();
+
+        fmt.debug_struct("Request")
+            .field("url", &redact_url(&self.url))
+            .field("method", &self.method)
+            .field("version", &self.version)
+            .field("headers", &headers)
+            .field("relay", &self.relay)
+            .field("target_form", &self.target_form)
+            .field("body_limit", &self.body_limit)
+            .field("limit_behavior", &self.limit_behavior)
+            .field("header_limit", &self.header_limit)
+            .field("chunkline_limit", &self.chunkline_limit)
+            .field("max_header_count", &self.max_header_count)
+            .field("status_line_limit", &self.status_line_limit)
+            .field("expect_continue", &self.expect_continue)
+            .field("capture_timing", &self.capture_timing)
+            .field("guess_content_type", &self.guess_content_type)
+            .field("deadline", &self.deadline)
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("tls_timeout", &self.tls_timeout)
+            .field("tcp_nodelay", &self.tcp_nodelay)
+            .field("tcp_keepalive", &self.tcp_keepalive)
+            .field("local_address", &self.local_address)
+            .field("resolved_addr", &self.resolved_addr)
+            .field("max_redirects", &self.max_redirects)
+            .finish()
+    }
 }
 
 impl fmt::Display for Request {
@@ -361,3 +1899,28 @@ impl From<Request> for String {
         item.to_string()
     }
 }
+
+fn is_valid_relay(value: &str) -> bool {
+    if value.starts_with('/') || value.starts_with("unix:") { // unix socket path
+        return value.trim_start_matches("unix:").len() > 1;
+    } else if value.starts_with("socks5://") || value.starts_with("socks5h://") {
+        return Url::parse(value).map_or(false, |url| url.host_str().map_or(false, |host| !host.is_empty()));
+    }
+
+    match value.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && !host.contains('/') && port.parse::<u16>().is_ok() && match host.strip_prefix('[') {
+            Some(host) => host.strip_suffix(']').map_or(false, |host| host.parse::<std::net::Ipv6Addr>().is_ok()),
+            None => !host.contains(':') && !host.contains(char::is_whitespace),
+        },
+        None => false,
+    }
+}
+
+fn format_quality(quality: f32) -> String {
+    let value = format!("{:.3}", quality); // at most three decimals
+    let value = value.trim_end_matches('0');
+    match value.ends_with('.') {
+        true => format!("{}0", value),
+        false => value.to_string(),
+    }
+}
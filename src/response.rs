@@ -3,10 +3,98 @@ use std::pin::Pin;
 use std::collections::HashMap;
 use std::collections::hash_map::RandomState;
 use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
 use std::str::FromStr;
-use async_std::io::{Read};
-use async_httplib::{Status, Version, read_exact, read_chunks};
-use crate::{read_content_length, read_transfer_encoding};
+use std::time::{Duration, SystemTime};
+use async_std::io::{self, Read, Write};
+use async_httplib::{Method, Status, Version, parse_version, parse_status, read_header_line};
+use url::Url;
+use crate::{Timing, ContentType, Event, Step, unfold, Duplex, ReadHalf, WriteHalf, ConnectionState, Permit, split, read_content_length, read_transfer_codings, is_chunked, read_chunked,
+    read_chunk_size, read_trailers, incomplete_chunked, read_line, read_sized, read_to_eof, parse_http_date, escape_bytes, check_content_length, check_framing};
+#[cfg(feature = "compression")]
+use crate::{Decoder, decode_body};
+use async_std::stream::Stream;
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusError {
+    status: Status,
+}
+
+impl StatusError {
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "The server responded with status `{} {}`.", self.status, self.status.reason())
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedResponse {
+    status: Status,
+    version: Version,
+    headers: HashMap<String, String>,
+    trailers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl OwnedResponse {
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    pub fn header<N: Into<String>>(&self, name: N) -> Option<&String> {
+        self.headers.get(&name.into())
+    }
+
+    pub fn trailers(&self) -> &HashMap<String, String> {
+        &self.trailers
+    }
+
+    pub fn body(&self) -> &Vec<u8> {
+        &self.body
+    }
+
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+}
+
+pub(crate) const HEADER_LIMIT: usize = 65536;
+pub(crate) const MAX_HEADER_COUNT: usize = 100;
+pub(crate) const STATUS_LINE_LIMIT: usize = 8192;
+pub(crate) const CHUNKLINE_LIMIT: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyEncoding {
+    Chunked,
+    Fixed(u64),
+    CloseDelimited,
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LimitBehavior {
+    Error,
+    Truncate,
+}
 
 pub struct Response<'a> {
     status: Status,
@@ -14,7 +102,35 @@ pub struct Response<'a> {
     headers: HashMap<String, String>,
     reader: Pin<Box<dyn Read + Send + Unpin + 'a>>,
     chunkline_limit: Option<usize>,
+    header_limit: Option<usize>,
+    max_header_count: usize,
     body_limit: Option<usize>,
+    limit_behavior: LimitBehavior,
+    truncated: bool,
+    timing: Option<Timing>,
+    negotiated_protocol: Option<String>,
+    final_url: Option<Url>,
+    redirect_history: Vec<Url>,
+    remote_addr: Option<SocketAddr>,
+    reused: bool,
+    trailers: HashMap<String, String>,
+    original_headers: Option<HashMap<String, String>>,
+    request_method: Option<Method>,
+    connection_state: Option<ConnectionState>,
+    permit: Option<Permit>,
+    broken: bool,
+    consumed: bool,
+    received: u64,
+    chunk_remaining: usize,
+    upgraded: Option<Box<dyn Duplex + 'a>>,
+    #[cfg(feature = "compression")]
+    sniff_compression: bool,
+    #[cfg(feature = "compression")]
+    decoder: Option<Decoder>,
+    #[cfg(feature = "compression")]
+    decoder_initialized: bool,
+    #[cfg(feature = "compression")]
+    decoded: u64,
 }
 
 impl<'a> Response<'a> {
@@ -26,7 +142,35 @@ impl<'a> Response<'a> {
             headers: HashMap::with_hasher(RandomState::new()),
             reader: Box::pin("".as_bytes()),
             chunkline_limit: None,
+            header_limit: Some(HEADER_LIMIT),
+            max_header_count: MAX_HEADER_COUNT,
             body_limit: None,
+            limit_behavior: LimitBehavior::Error,
+            truncated: false,
+            timing: None,
+            negotiated_protocol: None,
+            final_url: None,
+            redirect_history: Vec::new(),
+            remote_addr: None,
+            reused: false,
+            trailers: HashMap::with_hasher(RandomState::new()),
+            original_headers: None,
+            request_method: None,
+            connection_state: None,
+            permit: None,
+            broken: false,
+            consumed: false,
+            received: 0,
+            chunk_remaining: 0,
+            upgraded: None,
+            #[cfg(feature = "compression")]
+            sniff_compression: false,
+            #[cfg(feature = "compression")]
+            decoder: None,
+            #[cfg(feature = "compression")]
+            decoder_initialized: false,
+            #[cfg(feature = "compression")]
+            decoded: 0,
         }
     }
 
@@ -39,6 +183,16 @@ impl<'a> Response<'a> {
         res
     }
 
+    pub async fn read_from<R>(mut stream: R) -> Result<Self, Error>
+        where
+        R: Read + Send + Unpin + 'a,
+    {
+        let mut res = Self::default();
+        res.read_final_head(&mut stream, (STATUS_LINE_LIMIT, Some(HEADER_LIMIT), MAX_HEADER_COUNT), false).await?;
+        res.set_reader(stream);
+        Ok(res)
+    }
+
     pub fn status(&self) -> &Status {
         &self.status
     }
@@ -55,6 +209,25 @@ impl<'a> Response<'a> {
         self.headers.get(&name.into())
     }
 
+    pub fn original_headers(&self) -> &HashMap<String, String> {
+        match &self.original_headers {
+            Some(headers) => headers,
+            None => &self.headers,
+        }
+    }
+
+    pub fn trailers(&self) -> &HashMap<String, String> {
+        &self.trailers
+    }
+
+    pub fn trailer<N: Into<String>>(&self, name: N) -> Option<&String> {
+        self.trailers.get(&name.into())
+    }
+
+    pub fn request_method(&self) -> &Option<Method> {
+        &self.request_method
+    }
+
     pub fn reader(&self) -> &Pin<Box<dyn Read + Send + Unpin + 'a>> {
         &self.reader
     }
@@ -63,10 +236,51 @@ impl<'a> Response<'a> {
         &self.chunkline_limit
     }
 
+    pub fn header_limit(&self) -> &Option<usize> {
+        &self.header_limit
+    }
+
+    pub fn max_header_count(&self) -> usize {
+        self.max_header_count
+    }
+
     pub fn body_limit(&self) -> &Option<usize> {
         &self.body_limit
     }
 
+    pub fn limit_behavior(&self) -> LimitBehavior {
+        self.limit_behavior
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn sniff_compression(&self) -> bool {
+        self.sniff_compression
+    }
+
+    pub fn timing(&self) -> &Option<Timing> {
+        &self.timing
+    }
+
+    pub fn negotiated_protocol(&self) -> &Option<String> {
+        &self.negotiated_protocol
+    }
+
+    pub fn final_url(&self) -> &Option<Url> {
+        &self.final_url
+    }
+
+    pub fn redirect_history(&self) -> &Vec<Url> {
+        &self.redirect_history
+    }
+
+    pub fn redirect_count(&self) -> usize {
+        self.redirect_history.len()
+    }
+
+    pub fn remote_addr(&self) -> &Option<SocketAddr> {
+        &self.remote_addr
+    }
+
     pub fn has_status(&self, value: Status) -> bool {
         self.status == value
     }
@@ -83,10 +297,129 @@ impl<'a> Response<'a> {
         self.headers.contains_key(&name.into())
     }
 
+    pub fn reason(&self) -> &str {
+        self.status.reason()
+    }
+
+    pub fn is_informational(&self) -> bool {
+        self.status_class() == 1
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status_class() == 2
+    }
+
+    pub fn is_tunnel(&self) -> bool {
+        self.request_method == Some(Method::Connect) && self.is_success()
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        self.status_class() == 3
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        self.status_class() == 4
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        self.status_class() == 5
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.has_status(Status::PartialContent)
+    }
+
+    pub fn is_not_modified(&self) -> bool {
+        self.has_status(Status::NotModified)
+    }
+
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        match self.header("Allow") {
+            Some(allow) => allow.split(',')
+                .filter_map(|method| Method::from_str(method.trim()).ok())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn cors_allow_origin(&self) -> Option<&String> {
+        self.header("Access-Control-Allow-Origin")
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("Content-Length")?.trim().parse::<u64>().ok()
+    }
+
+    pub fn content_type(&self) -> Option<ContentType> {
+        ContentType::from_str(self.header("Content-Type")?).ok()
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.header("Retry-After")?.trim();
+        match value.parse::<u64>() {
+            Ok(seconds) => Some(Duration::from_secs(seconds)),
+            Err(_) => match parse_http_date(value)?.duration_since(SystemTime::now()) {
+                Ok(delay) => Some(delay),
+                Err(_) => Some(Duration::from_secs(0)),
+            },
+        }
+    }
+
+    pub fn content_range(&self) -> Option<(u64, u64, Option<u64>)> {
+        let value = self.header("Content-Range")?.trim();
+        let value = value.strip_prefix("bytes")?.trim_start();
+        let (range, total) = value.split_once('/')?;
+        let (start, end) = range.split_once('-')?;
+        let start = start.trim().parse::<u64>().ok()?;
+        let end = end.trim().parse::<u64>().ok()?;
+        let total = match total.trim() {
+            "*" => None,
+            total => Some(total.parse::<u64>().ok()?),
+        };
+        match start <= end {
+            true => Some((start, end, total)),
+            false => None,
+        }
+    }
+
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+
+    pub fn is_consumed(&self) -> bool {
+        self.consumed
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn body_encoding(&self) -> BodyEncoding {
+        if self.forbids_body() || self.is_tunnel() || self.request_method == Some(Method::Head) {
+            BodyEncoding::Empty
+        } else if is_chunked(&self.headers) {
+            BodyEncoding::Chunked
+        } else if self.has_header("Transfer-Encoding") { // not chunked, ends with the connection
+            BodyEncoding::CloseDelimited
+        } else if self.has_header("Content-Length") {
+            BodyEncoding::Fixed(self.content_length().unwrap_or(0))
+        } else { // no declared length, the body ends with the connection or stream
+            BodyEncoding::CloseDelimited
+        }
+    }
+
+    pub fn is_upgraded(&self) -> bool {
+        self.upgraded.is_some()
+    }
+
     pub fn has_chunkline_limit(&self) -> bool {
         self.chunkline_limit.is_some()
     }
 
+    pub fn has_header_limit(&self) -> bool {
+        self.header_limit.is_some()
+    }
+
     pub fn has_body_limit(&self) -> bool {
         self.body_limit.is_some()
     }
@@ -120,14 +453,70 @@ impl<'a> Response<'a> {
         self.reader = Box::pin(reader);
     }
 
+    pub fn set_upgraded<S>(&mut self, stream: S)
+        where
+        S: Read + Write + Send + Unpin + 'a,
+    {
+        self.upgraded = Some(Box::new(stream));
+    }
+
+    pub fn set_request_method(&mut self, value: Method) {
+        self.request_method = Some(value);
+    }
+
     pub fn set_chunkline_limit(&mut self, length: usize) {
         self.chunkline_limit = Some(length);
     }
 
+    pub fn set_header_limit(&mut self, length: usize) {
+        self.header_limit = Some(length);
+    }
+
+    pub fn set_max_header_count(&mut self, value: usize) {
+        self.max_header_count = value;
+    }
+
     pub fn set_body_limit(&mut self, length: usize) {
         self.body_limit = Some(length);
     }
 
+    pub fn set_limit_behavior(&mut self, value: LimitBehavior) {
+        self.limit_behavior = value;
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn set_sniff_compression(&mut self, value: bool) {
+        self.sniff_compression = value;
+    }
+
+    pub fn set_timing(&mut self, value: Timing) {
+        self.timing = Some(value);
+    }
+
+    pub fn set_negotiated_protocol<V: Into<String>>(&mut self, value: V) {
+        self.negotiated_protocol = Some(value.into());
+    }
+
+    pub fn set_final_url(&mut self, value: Url) {
+        self.final_url = Some(value);
+    }
+
+    pub fn set_redirect_history(&mut self, value: Vec<Url>) {
+        self.redirect_history = value;
+    }
+
+    pub fn set_remote_addr(&mut self, value: SocketAddr) {
+        self.remote_addr = Some(value);
+    }
+
+    pub fn set_reused(&mut self, value: bool) {
+        self.reused = value;
+    }
+
+    pub fn remove_header_limit(&mut self) {
+        self.header_limit = None;
+    }
+
     pub fn remove_header<N: Into<String>>(&mut self, name: N) {
         self.headers.remove(&name.into());
     }
@@ -150,19 +539,434 @@ impl<'a> Response<'a> {
         output
     }
 
+    pub fn error_for_status(self) -> Result<Self, Error> {
+        match self.is_client_error() || self.is_server_error() {
+            true => Err(Error::new(ErrorKind::Other, StatusError { status: self.status })),
+            false => Ok(self),
+        }
+    }
+
+    pub fn into_upgraded(self) -> Option<Box<dyn Duplex + 'a>> {
+        match self.is_switching() {
+            true => self.upgraded,
+            false => None,
+        }
+    }
+
+    pub fn into_parts(self) -> Option<(ReadHalf<'a>, WriteHalf<'a>)> {
+        self.into_upgraded().map(split)
+    }
+
     pub async fn recv(&mut self) -> Result<Vec<u8>, Error> {
         let mut data = Vec::new();
+        self.recv_into(&mut data).await?;
+        Ok(data)
+    }
 
-        if read_transfer_encoding(&self.headers) == "chunked" {
-            read_chunks(&mut self.reader, &mut data, (self.chunkline_limit, self.body_limit)).await?;
-        } else if self.has_header("Content-Length") {
-            let length = read_content_length(&self.headers, self.body_limit)?;
-            read_exact(&mut self.reader, &mut data, length).await?;
+    pub async fn recv_into(&mut self, buffer: &mut Vec<u8>) -> Result<usize, Error> {
+        if self.broken {
+            return Err(Error::new(ErrorKind::InvalidData, "The response body could not be read completely before."));
+        } else if self.consumed {
+            return Err(Error::new(ErrorKind::Other, "The response body has already been consumed."));
+        }
+
+        buffer.clear();
+        if let Err(e) = self.read_body(buffer).await {
+            self.mark_broken();
+            return Err(e);
         }
+        self.mark_consumed();
 
+        let data = self.decode_transfer(std::mem::take(buffer))?; // keeps the allocation when nothing is decoded
+        #[cfg(feature = "compression")]
+        let data = self.decode(data)?;
+
+        *buffer = data;
+        Ok(buffer.len())
+    }
+
+    pub async fn recv_timeout(&mut self, duration: Duration) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        self.recv_into_timeout(&mut data, duration).await?;
         Ok(data)
     }
 
+    pub async fn recv_into_timeout(&mut self, buffer: &mut Vec<u8>, duration: Duration) -> Result<usize, Error> {
+        match io::timeout(duration, self.recv_into(buffer)).await {
+            Err(e) if e.kind() == ErrorKind::TimedOut && !self.broken && !self.consumed => { // partial data stays in the buffer
+                self.mark_broken();
+                Err(Error::new(ErrorKind::TimedOut, "The response body read timeout has been exceeded."))
+            },
+            result => result,
+        }
+    }
+
+    pub async fn bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.recv().await
+    }
+
+    pub async fn into_owned(mut self) -> Result<OwnedResponse, Error> {
+        let body = self.recv().await?;
+        Ok(OwnedResponse {
+            status: self.status,
+            version: self.version,
+            headers: std::mem::take(&mut self.headers),
+            trailers: std::mem::take(&mut self.trailers),
+            body,
+        })
+    }
+
+    #[cfg(not(feature = "compression"))]
+    pub async fn recv_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut buffer = vec![0u8; 8192];
+        match self.next_chunk(&mut buffer).await? {
+            Some(size) => {
+                buffer.truncate(size);
+                Ok(Some(buffer))
+            },
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    pub async fn recv_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut buffer = vec![0u8; 8192];
+        loop {
+            let size = self.next_chunk(&mut buffer).await?;
+            if !self.decoder_initialized {
+                self.decoder_initialized = true;
+                self.decoder = match self.header("Content-Encoding") {
+                    Some(encoding) => Decoder::new(encoding), // unknown encodings pass through like in `recv`
+                    None if self.sniff_compression && buffer[..size.unwrap_or(0)].starts_with(&[0x1f, 0x8b]) => Decoder::new("gzip"),
+                    None => None,
+                };
+            }
+
+            let decoder = match &mut self.decoder {
+                Some(decoder) => decoder,
+                None => return Ok(size.map(|size| buffer[..size].to_vec())),
+            };
+            let data = match size {
+                Some(size) => decoder.push(&buffer[..size]),
+                None => decoder.finish(),
+            };
+            let data = match data {
+                Ok(data) => data,
+                Err(e) => {
+                    self.mark_broken();
+                    return Err(e);
+                },
+            };
+
+            self.decoded += data.len() as u64;
+            if let Some(limit) = self.body_limit {
+                if self.decoded > limit as u64 {
+                    self.mark_broken();
+                    return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while decoding the HTTP body data.", limit)));
+                }
+            }
+
+            if size.is_none() {
+                self.decoder = None;
+                self.original_headers = Some(self.headers.clone());
+                self.remove_header("Content-Encoding");
+                if self.has_header("Content-Length") {
+                    self.set_header("Content-Length", self.decoded.to_string());
+                }
+                return Ok(if data.is_empty() { None } else { Some(data) });
+            } else if !data.is_empty() {
+                return Ok(Some(data));
+            }
+        }
+    }
+
+    pub async fn discard_body(&mut self) -> Result<u64, Error> {
+        let mut buffer = [0u8; 8192];
+        let mut total = 0;
+        while let Some(size) = self.next_chunk(&mut buffer).await? {
+            total += size as u64;
+        }
+        Ok(total)
+    }
+
+    async fn next_chunk(&mut self, buffer: &mut [u8]) -> Result<Option<usize>, Error> {
+        if self.broken {
+            return Err(Error::new(ErrorKind::InvalidData, "The response body could not be read completely before."));
+        } else if self.consumed {
+            return Ok(None);
+        }
+
+        match self.read_chunk(buffer).await {
+            Ok(Some(size)) => Ok(Some(size)),
+            Ok(None) => {
+                self.mark_consumed();
+                Ok(None)
+            },
+            Err(e) => {
+                self.mark_broken();
+                Err(e)
+            },
+        }
+    }
+
+    fn mark_consumed(&mut self) {
+        self.consumed = true;
+        self.permit = None; // frees the per-host slot once the body is read
+        if let Some(state) = &self.connection_state {
+            state.set_idle();
+        }
+    }
+
+    fn mark_broken(&mut self) {
+        self.broken = true;
+        self.permit = None;
+        if let Some(state) = &self.connection_state {
+            state.set_broken();
+        }
+    }
+
+    async fn read_chunk(&mut self, buffer: &mut [u8]) -> Result<Option<usize>, Error> { // raw body data, as transferred
+        let size = match self.body_encoding() {
+            BodyEncoding::Empty => return Ok(None),
+            BodyEncoding::Chunked => match self.read_chunk_part(buffer).await.map_err(incomplete_chunked)? {
+                Some(size) => size,
+                None => return Ok(None),
+            },
+            BodyEncoding::Fixed(length) => {
+                let remaining = length - self.received;
+                if remaining == 0 {
+                    return Ok(None);
+                }
+                let size = self.reader.read(&mut buffer[..remaining.min(buffer.len() as u64) as usize]).await?;
+                if size == 0 {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, format!("The response body is incomplete, {} of {} bytes are missing.", remaining, length)));
+                }
+                size
+            },
+            BodyEncoding::CloseDelimited => {
+                let size = self.reader.read(buffer).await?;
+                if size == 0 {
+                    if let Some(state) = &self.connection_state {
+                        state.set_broken();
+                    }
+                    return Ok(None);
+                }
+                size
+            },
+        };
+
+        self.received += size as u64;
+        if let Some(limit) = self.body_limit {
+            if self.received > limit as u64 {
+                return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP body data.", limit)));
+            }
+        }
+        Ok(Some(size))
+    }
+
+    async fn read_chunk_part(&mut self, buffer: &mut [u8]) -> Result<Option<usize>, Error> {
+        if self.chunk_remaining == 0 {
+            self.chunk_remaining = read_chunk_size(&mut self.reader, self.chunkline_limit).await?;
+            if self.chunk_remaining == 0 {
+                self.trailers.clear();
+                read_trailers(&mut self.reader, &mut self.trailers, (self.header_limit, self.max_header_count)).await?;
+                return Ok(None);
+            }
+        }
+        let size = self.reader.read(&mut buffer[..self.chunk_remaining.min(buffer.len())]).await?;
+        if size == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "The stream ended while reading the HTTP body chunk data."));
+        }
+        self.chunk_remaining -= size;
+        if self.chunk_remaining == 0 && !read_line(&mut self.reader, Some(0)).await?.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "The chunk data is not terminated with CRLF."));
+        }
+        Ok(Some(size))
+    }
+
+    async fn recv_line(&mut self, buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+        loop {
+            if let Some(index) = buffer.iter().position(|byte| *byte == b'\n') {
+                let mut line: Vec<u8> = buffer.drain(..=index).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return Ok(Some(line));
+            }
+            match self.recv_chunk().await? {
+                Some(data) => buffer.extend_from_slice(&data),
+                None if buffer.is_empty() => return Ok(None),
+                None => return Ok(Some(std::mem::take(buffer))), // last line without terminator
+            };
+        }
+    }
+
+    async fn read_body(&mut self, data: &mut Vec<u8>) -> Result<(), Error> {
+        match self.body_encoding() {
+            BodyEncoding::Empty => {},
+            BodyEncoding::Chunked => {
+                self.trailers.clear();
+                read_chunked(&mut self.reader, data, &mut self.trailers, (self.chunkline_limit, self.body_limit), (self.header_limit, self.max_header_count)).await?;
+            },
+            BodyEncoding::Fixed(length) => match (self.limit_behavior, self.body_limit) {
+                (LimitBehavior::Truncate, Some(limit)) if length > limit as u64 => {
+                    read_sized(&mut self.reader, data, limit).await?;
+                    self.truncated = true;
+                    if let Some(state) = &self.connection_state {
+                        state.set_broken(); // the rest of the body is left unread
+                    }
+                },
+                _ => {
+                    let length = read_content_length(&self.headers, self.body_limit)?;
+                    read_sized(&mut self.reader, data, length).await?;
+                },
+            },
+            BodyEncoding::CloseDelimited => {
+                read_to_eof(&mut self.reader, data, self.body_limit).await?;
+                if let Some(state) = &self.connection_state {
+                    state.set_broken();
+                }
+            },
+        };
+
+        Ok(())
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        if self.body_encoding() == BodyEncoding::CloseDelimited { // only the close ends the body
+            return false;
+        }
+        match self.version {
+            Version::Http0_9 => false,
+            Version::Http1_0 => self.has_connection_token("keep-alive"), // opt-in before HTTP/1.1
+            _ => !self.is_closing(),
+        }
+    }
+
+    fn is_closing(&self) -> bool {
+        self.has_connection_token("close")
+    }
+
+    fn has_connection_token(&self, token: &str) -> bool {
+        match self.header("Connection") {
+            Some(value) => value.split(',').any(|item| item.trim().eq_ignore_ascii_case(token)),
+            None => false,
+        }
+    }
+
+    fn forbids_body(&self) -> bool {
+        self.has_status(Status::NoContent) || self.is_not_modified() || self.is_informational()
+    }
+
+    pub(crate) fn is_interim(&self) -> bool {
+        self.is_informational() && !self.has_status(Status::SwitchingProtocols) // 101 is final
+    }
+
+    pub(crate) fn is_switching(&self) -> bool {
+        self.has_status(Status::SwitchingProtocols) || self.is_tunnel() // the stream no longer speaks HTTP
+    }
+
+    fn status_class(&self) -> u16 {
+        self.status as u16 / 100
+    }
+
+    pub(crate) async fn read_final_head<R>(&mut self, stream: &mut R, limits: (usize, Option<usize>, usize), lenient: bool) -> Result<(), Error>
+        where
+        R: Read + Unpin,
+    {
+        loop { // skip interim responses
+            self.read_head(stream, limits, lenient).await?;
+            if !self.is_interim() {
+                return Ok(());
+            }
+            self.clear_headers();
+        }
+    }
+
+    pub(crate) async fn read_head<R>(&mut self, stream: &mut R, limits: (usize, Option<usize>, usize), lenient: bool) -> Result<(), Error>
+        where
+        R: Read + Unpin,
+    {
+        let (status_line_limit, header_limit, max_header_count) = limits;
+        self.read_status(stream, status_line_limit, lenient).await?;
+        self.read_headers(stream, (header_limit, max_header_count), lenient).await
+    }
+
+    async fn read_status<R>(&mut self, stream: &mut R, limit: usize, lenient: bool) -> Result<(), Error>
+        where
+        R: Read + Unpin,
+    {
+        let line = match read_status_line(stream, limit, lenient).await? {
+            Some(line) => line,
+            None => return Err(Error::new(ErrorKind::UnexpectedEof, "The server closed the connection without sending a response.")),
+        };
+        let (version, status) = parse_status_line(&line)?;
+        self.version = version;
+        self.status = status;
+        Ok(())
+    }
+
+    async fn read_headers<R>(&mut self, stream: &mut R, limits: (Option<usize>, usize), lenient: bool) -> Result<(), Error>
+        where
+        R: Read + Unpin,
+    {
+        read_header_fields(stream, &mut self.headers, limits, lenient, "response").await?;
+        check_framing(self.headers())?;
+        Ok(())
+    }
+
+    pub(crate) fn set_connection_state(&mut self, state: ConnectionState) {
+        self.connection_state = Some(state);
+    }
+
+    pub(crate) fn set_permit(&mut self, permit: Permit) {
+        if self.body_encoding() != BodyEncoding::Empty { // bodyless responses free the slot right away
+            self.permit = Some(permit);
+        }
+    }
+
+    fn decode_transfer(&self, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        read_transfer_codings(&self.headers).iter().rev() // applied in order, undone in reverse
+            .try_fold(data, |data, coding| self.decode_coding(coding, data))
+    }
+
+    fn decode_coding(&self, coding: &str, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if coding == "chunked" || coding == "identity" {
+            return Ok(data);
+        }
+        #[cfg(feature = "compression")]
+        if let Some(decoded) = decode_body(coding, &data, self.body_limit)? {
+            return Ok(decoded);
+        }
+        Err(Error::new(ErrorKind::InvalidData, format!("The transfer coding `{}` is not supported.", coding)))
+    }
+
+    #[cfg(feature = "compression")]
+    fn decode(&mut self, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let encoding = match self.header("Content-Encoding") {
+            Some(encoding) => encoding.to_string(),
+            None if self.sniff_compression && data.starts_with(&[0x1f, 0x8b]) => "gzip".to_string(), // gzip magic without the header
+            None => return Ok(data),
+        };
+
+        match decode_body(&encoding, &data, self.body_limit)? {
+            Some(decoded) => {
+                self.original_headers = Some(self.headers.clone());
+                self.remove_header("Content-Encoding");
+                if self.has_header("Content-Length") {
+                    self.set_header("Content-Length", decoded.len().to_string());
+                }
+                Ok(decoded)
+            },
+            None => Ok(data),
+        }
+    }
+
+    pub fn events(self) -> impl Stream<Item = Result<Event, Error>> + 'a {
+        unfold((self, Vec::new(), None, false), |state| -> Step<'a, _, Result<Event, Error>> { Box::pin(next_event(state)) })
+    }
+
     pub async fn recv_string(&mut self) -> Result<String, Error> {
         let data = self.recv().await?;
         let txt = match String::from_utf8(data) {
@@ -184,6 +988,197 @@ impl<'a> Response<'a> {
         };
         Ok(json)
     }
+
+    #[cfg(feature = "json")]
+    pub fn json_lines<T>(self) -> impl Stream<Item = Result<T, Error>> + 'a
+        where
+        T: DeserializeOwned + Send + 'a,
+    {
+        unfold((self, Vec::new(), false), |state| -> Step<'a, _, Result<T, Error>> { Box::pin(next_json_line::<T>(state)) })
+    }
+}
+
+async fn next_event<'a>(state: (Response<'a>, Vec<u8>, Option<String>, bool)) -> Option<(Result<Event, Error>, (Response<'a>, Vec<u8>, Option<String>, bool))> {
+    let (mut res, mut buffer, last_id, done) = state;
+    if done {
+        return None;
+    }
+
+    let mut event = Event::with_id(last_id); // the last event id carries over
+    loop {
+        let line = match res.recv_line(&mut buffer).await {
+            Ok(Some(line)) => line,
+            Ok(None) => return None, // incomplete event is discarded
+            Err(e) => return Some((Err(e), (res, buffer, None, true))),
+        };
+        if !line.is_empty() {
+            event.read_field(&String::from_utf8_lossy(&line));
+        } else if event.is_empty() {
+            event = Event::with_id(event.id().clone());
+        } else {
+            event.finish();
+            let last_id = event.id().clone();
+            return Some((Ok(event), (res, buffer, last_id, false)));
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+async fn next_json_line<'a, T>(state: (Response<'a>, Vec<u8>, bool)) -> Option<(Result<T, Error>, (Response<'a>, Vec<u8>, bool))>
+    where
+    T: DeserializeOwned + Send + 'a,
+{
+    let (mut res, mut buffer, done) = state;
+    if done {
+        return None;
+    }
+
+    loop {
+        let line = match res.recv_line(&mut buffer).await {
+            Ok(Some(line)) => line,
+            Ok(None) => return None,
+            Err(e) => return Some((Err(e), (res, buffer, true))),
+        };
+        if line.iter().all(|byte| byte.is_ascii_whitespace()) {
+            continue;
+        }
+        let item = match serde_json::from_slice(&line) {
+            Ok(item) => Ok(item),
+            Err(e) => Err(Error::new(ErrorKind::InvalidData, e.to_string())),
+        };
+        return Some((item, (res, buffer, false)));
+    }
+}
+
+async fn read_status_line<R>(stream: &mut R, limit: usize, lenient: bool) -> Result<Option<Vec<u8>>, Error>
+    where
+    R: Read + Unpin,
+{
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+
+    loop {
+        if stream.read(&mut byte).await? == 0 {
+            return match line.is_empty() {
+                true => Ok(None),
+                false => Err(Error::new(ErrorKind::UnexpectedEof, "The stream ended while reading the HTTP line.")),
+            };
+        }
+        match byte[0] {
+            b'\n' if line.last() == Some(&b'\r') => {
+                line.pop();
+                return Ok(Some(line));
+            },
+            b'\n' if lenient => return Ok(Some(line)), // bare LF
+            byte => line.push(byte),
+        }
+        if byte[0] != b'\r' && line.len() > limit {
+            return match line.starts_with(b"HTTP/") {
+                true => Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP status line.", limit))),
+                false => Err(Error::new(ErrorKind::InvalidData, format!("The response does not look like HTTP: `{}`.", escape_bytes(&line, 64)))),
+            };
+        }
+    }
+}
+
+pub(crate) async fn read_header_fields<R>(stream: &mut R, headers: &mut HashMap<String, String>, limits: (Option<usize>, usize), lenient: bool, kind: &str) -> Result<(), Error>
+    where
+    R: Read + Unpin,
+{
+    let (limit, max_count) = limits;
+    let (mut size, mut count) = (0, 0);
+    loop {
+        if count >= max_count {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} header lines while reading the HTTP headers.", max_count)));
+        }
+
+        let remaining = match limit {
+            Some(limit) if size >= limit => return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP headers.", limit))),
+            Some(limit) => Some(limit - size),
+            None => None,
+        };
+        let (mut name, mut value) = (vec![], vec![]);
+        match lenient {
+            true => read_lenient_header_line(stream, (&mut name, &mut value), remaining).await?,
+            false => read_header_line(stream, (&mut name, &mut value), remaining).await?,
+        };
+
+        if name.is_empty() {
+            return Ok(());
+        }
+        size += name.len() + value.len() + 4;
+        count += 1;
+
+        let name = match String::from_utf8(name) {
+            Ok(name) => name,
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData, format!("The {} header `#{}` is invalid.", kind, headers.len()))),
+        };
+        let mut value = match String::from_utf8(value) {
+            Ok(value) => value,
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData, format!("The {} header `#{}` is invalid.", kind, headers.len()))),
+        };
+        if name.eq_ignore_ascii_case("Content-Length") {
+            value = check_content_length(headers, &value)?;
+        }
+        headers.insert(name, value);
+    }
+}
+
+async fn read_lenient_header_line<R>(stream: &mut R, header: (&mut Vec<u8>, &mut Vec<u8>), limit: Option<usize>) -> Result<(), Error>
+    where
+    R: Read + Unpin,
+{
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+
+    loop {
+        if stream.read(&mut byte).await? == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "The stream ended while reading the HTTP line."));
+        } else if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+        if let Some(limit) = limit {
+            if line.len() > limit + 1 { // room for a trailing CR
+                return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP line.", limit)));
+            }
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let (name, value) = header;
+    match line.iter().position(|byte| *byte == b':') {
+        Some(index) if index > 0 => {
+            name.extend_from_slice(&line[..index]);
+            value.extend(line[index + 1..].iter().skip_while(|byte| byte.is_ascii_whitespace()));
+            while value.last().map_or(false, |byte| byte.is_ascii_whitespace()) {
+                value.pop();
+            }
+            Ok(())
+        },
+        _ => Err(Error::new(ErrorKind::InvalidData, format!("The response header `{}` is invalid.", escape_bytes(&line, 64)))),
+    }
+}
+
+fn parse_status_line(line: &[u8]) -> Result<(Version, Status), Error> {
+    let invalid = || Error::new(ErrorKind::InvalidData, format!("The response status line `{}` is invalid.", escape_bytes(line, 64)));
+
+    if !line.starts_with(b"HTTP/") {
+        return Err(Error::new(ErrorKind::InvalidData, format!("The response does not look like HTTP: `{}`.", escape_bytes(line, 64))));
+    }
+
+    let mut parts = line.splitn(3, |byte| *byte == b' ');
+    let version = parts.next().ok_or_else(invalid)?;
+    let status = parts.next().ok_or_else(invalid)?;
+    match (parse_version(version.to_vec()), parse_status(status.to_vec())) {
+        (Ok(version), Ok(status)) => Ok((version, status)),
+        _ => Err(invalid()),
+    }
 }
 
 impl fmt::Display for Response<'_> {
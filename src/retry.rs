@@ -0,0 +1,53 @@
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use async_std::io::Write;
+
+pub struct RetryWrite<'a, S> {
+    inner: &'a mut S,
+    retries: usize,
+}
+
+impl<'a, S> RetryWrite<'a, S>
+    where
+    S: Write + Unpin,
+{
+
+    pub fn new(inner: &'a mut S, retries: usize) -> Self {
+        Self { inner, retries }
+    }
+
+    fn retry<T>(&mut self, cx: &mut Context<'_>, poll: Poll<Result<T, Error>>) -> Poll<Result<T, Error>> {
+        match poll {
+            Poll::Ready(Err(e)) if is_retryable(&e) && self.retries > 0 => {
+                self.retries -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            },
+            poll => poll,
+        }
+    }
+}
+
+impl<'a, S> Write for RetryWrite<'a, S>
+    where
+    S: Write + Unpin,
+{
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let poll = Pin::new(&mut *self.inner).poll_write(cx, buf);
+        self.retry(cx, poll)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let poll = Pin::new(&mut *self.inner).poll_flush(cx);
+        self.retry(cx, poll)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut *self.inner).poll_close(cx)
+    }
+}
+
+fn is_retryable(e: &Error) -> bool {
+    e.kind() == ErrorKind::Interrupted || e.kind() == ErrorKind::WouldBlock
+}
@@ -0,0 +1,107 @@
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use async_std::io::{Read, Write};
+use async_std::prelude::*;
+
+const VERSION: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const USER_PASS: u8 = 0x02;
+const NO_METHODS: u8 = 0xff;
+
+pub async fn socks5_handshake<S>(stream: &mut S, host: &str, port: u16, auth: Option<(String, String)>) -> Result<(), Error>
+    where
+    S: Read + Write + Unpin,
+{
+    let greeting = match auth {
+        Some(_) => vec![VERSION, 2, NO_AUTH, USER_PASS],
+        None => vec![VERSION, 1, NO_AUTH],
+    };
+    stream.write_all(&greeting).await?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(Error::new(ErrorKind::InvalidData, "The SOCKS proxy replied with an invalid version."));
+    }
+    match (reply[1], auth) {
+        (NO_AUTH, _) => {},
+        (USER_PASS, Some((username, password))) => socks5_authenticate(stream, &username, &password).await?,
+        (NO_METHODS, _) | (USER_PASS, None) => return Err(Error::new(ErrorKind::PermissionDenied, "The SOCKS proxy rejected all authentication methods.")),
+        (method, _) => return Err(Error::new(ErrorKind::InvalidData, format!("The SOCKS proxy selected an unsupported method `{}`.", method))),
+    };
+
+    let mut request = vec![VERSION, 0x01, 0x00]; // connect
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        },
+        Ok(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        },
+        Err(_) if host.len() <= 255 => { // resolved by the proxy
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        },
+        Err(_) => return Err(Error::new(ErrorKind::InvalidInput, format!("The host `{}` is too long for SOCKS.", host))),
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(Error::new(ErrorKind::ConnectionRefused, format!("The SOCKS proxy failed to connect to `{}:{}` ({}).", host, port, socks5_reason(reply[1]))));
+    }
+    let length = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut length = [0u8; 1];
+            stream.read_exact(&mut length).await?;
+            length[0] as usize
+        },
+        kind => return Err(Error::new(ErrorKind::InvalidData, format!("The SOCKS proxy replied with an invalid address type `{}`.", kind))),
+    };
+    let mut bound = vec![0u8; length + 2]; // address and port
+    stream.read_exact(&mut bound).await?;
+
+    Ok(())
+}
+
+async fn socks5_authenticate<S>(stream: &mut S, username: &str, password: &str) -> Result<(), Error>
+    where
+    S: Read + Write + Unpin,
+{
+    if username.len() > 255 || password.len() > 255 {
+        return Err(Error::new(ErrorKind::InvalidInput, "The SOCKS credentials are too long."));
+    }
+    let mut request = vec![0x01, username.len() as u8];
+    request.extend_from_slice(username.as_bytes());
+    request.push(password.len() as u8);
+    request.extend_from_slice(password.as_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match reply[1] {
+        0x00 => Ok(()),
+        _ => Err(Error::new(ErrorKind::PermissionDenied, "The SOCKS proxy rejected the credentials.")),
+    }
+}
+
+fn socks5_reason(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
@@ -0,0 +1,51 @@
+use std::io::Error;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use async_std::io::{Read, Write};
+
+pub struct Tap<'a, S> {
+    inner: &'a mut S,
+    written: &'a mut Vec<u8>,
+    read: &'a mut Vec<u8>,
+}
+
+impl<'a, S> Tap<'a, S> {
+
+    pub fn new(inner: &'a mut S, written: &'a mut Vec<u8>, read: &'a mut Vec<u8>) -> Self {
+        Self { inner, written, read }
+    }
+}
+
+impl<'a, S> Read for Tap<'a, S>
+    where
+    S: Read + Unpin,
+{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let poll = Pin::new(&mut *self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(size)) = poll {
+            self.read.extend_from_slice(&buf[..size]);
+        }
+        poll
+    }
+}
+
+impl<'a, S> Write for Tap<'a, S>
+    where
+    S: Write + Unpin,
+{
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        let poll = Pin::new(&mut *self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(size)) = poll {
+            self.written.extend_from_slice(&buf[..size]);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut *self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Pin::new(&mut *self.inner).poll_close(cx)
+    }
+}
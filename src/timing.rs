@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timing {
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    first_byte: Option<Duration>,
+    total: Option<Duration>,
+}
+
+impl Timing {
+
+    pub fn dns(&self) -> &Option<Duration> {
+        &self.dns
+    }
+
+    pub fn connect(&self) -> &Option<Duration> {
+        &self.connect
+    }
+
+    pub fn tls(&self) -> &Option<Duration> {
+        &self.tls
+    }
+
+    pub fn first_byte(&self) -> &Option<Duration> {
+        &self.first_byte
+    }
+
+    pub fn total(&self) -> &Option<Duration> {
+        &self.total
+    }
+
+    pub(crate) fn set_dns(&mut self, value: Duration) {
+        self.dns = Some(value);
+    }
+
+    pub(crate) fn set_connect(&mut self, value: Duration) {
+        self.connect = Some(value);
+    }
+
+    pub(crate) fn set_tls(&mut self, value: Duration) {
+        self.tls = Some(value);
+    }
+
+    pub(crate) fn set_first_byte(&mut self, value: Duration) {
+        self.first_byte = Some(value);
+    }
+
+    pub(crate) fn set_total(&mut self, value: Duration) {
+        self.total = Some(value);
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Error, ErrorKind};
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use async_std::io::{Read, Write};
+use async_std::future::poll_fn;
+use native_tls::{HandshakeError, MidHandshakeTlsStream, TlsConnector};
+
+pub(crate) struct TlsStream<S> {
+    inner: native_tls::TlsStream<Adapter<S>>,
+}
+
+impl<S: Read + Write + Unpin> TlsStream<S> {
+
+    pub fn negotiated_alpn(&self) -> Option<String> {
+        match self.inner.negotiated_alpn() {
+            Ok(Some(protocol)) => String::from_utf8(protocol).ok(),
+            _ => None,
+        }
+    }
+
+    fn with_context<F, T>(&mut self, cx: &mut Context, f: F) -> Poll<Result<T, Error>>
+        where
+        F: FnOnce(&mut native_tls::TlsStream<Adapter<S>>) -> Result<T, Error>,
+    {
+        self.inner.get_mut().waker = Some(cx.waker().clone());
+        match f(&mut self.inner) {
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Poll::Pending,
+            result => Poll::Ready(result),
+        }
+    }
+}
+
+impl<S: Read + Write + Unpin> Read for TlsStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        self.with_context(cx, |stream| io::Read::read(stream, buf))
+    }
+}
+
+impl<S: Read + Write + Unpin> Write for TlsStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<Result<usize, Error>> {
+        self.with_context(cx, |stream| io::Write::write(stream, buf))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Error>> {
+        self.with_context(cx, |stream| io::Write::flush(stream))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Error>> {
+        match self.with_context(cx, |stream| stream.shutdown()) {
+            Poll::Ready(Ok(())) => Pin::new(&mut self.inner.get_mut().inner).poll_close(cx),
+            result => result,
+        }
+    }
+}
+
+struct Adapter<S> {
+    inner: S,
+    waker: Option<Waker>,
+}
+
+impl<S: Unpin> Adapter<S> {
+
+    fn poll<F, T>(&mut self, f: F) -> Result<T, Error>
+        where
+        F: FnOnce(Pin<&mut S>, &mut Context) -> Poll<Result<T, Error>>,
+    {
+        let waker = match &self.waker {
+            Some(waker) => waker.clone(),
+            None => return Err(Error::from(ErrorKind::WouldBlock)),
+        };
+        match f(Pin::new(&mut self.inner), &mut Context::from_waker(&waker)) {
+            Poll::Ready(result) => result,
+            Poll::Pending => Err(Error::from(ErrorKind::WouldBlock)), // native-tls retries once woken
+        }
+    }
+}
+
+impl<S: Read + Unpin> io::Read for Adapter<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.poll(|stream, cx| stream.poll_read(cx, buf))
+    }
+}
+
+impl<S: Write + Unpin> io::Write for Adapter<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.poll(|stream, cx| stream.poll_write(cx, buf))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.poll(|stream, cx| stream.poll_flush(cx))
+    }
+}
+
+enum Handshake<S> {
+    Start(Adapter<S>),
+    Pending(MidHandshakeTlsStream<Adapter<S>>),
+    Done,
+}
+
+pub(crate) async fn tls_handshake<S>(connector: &TlsConnector, domain: &str, stream: S) -> Result<TlsStream<S>, Error>
+    where
+    S: Read + Write + Unpin,
+{
+    let mut state = Handshake::Start(Adapter { inner: stream, waker: None });
+    poll_fn(|cx| {
+        let result = match std::mem::replace(&mut state, Handshake::Done) {
+            Handshake::Start(mut adapter) => {
+                adapter.waker = Some(cx.waker().clone());
+                connector.connect(domain, adapter)
+            },
+            Handshake::Pending(mut stream) => {
+                stream.get_mut().waker = Some(cx.waker().clone());
+                stream.handshake()
+            },
+            Handshake::Done => return Poll::Ready(Err(Error::new(ErrorKind::Other, "The TLS handshake has already completed."))),
+        };
+        match result {
+            Ok(inner) => Poll::Ready(Ok(TlsStream { inner })),
+            Err(HandshakeError::WouldBlock(stream)) => {
+                state = Handshake::Pending(stream);
+                Poll::Pending
+            },
+            Err(HandshakeError::Failure(e)) => Poll::Ready(Err(Error::new(ErrorKind::Interrupted, e.to_string()))),
+        }
+    }).await
+}
@@ -1,20 +1,188 @@
 use std::collections::HashMap;
 use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use async_std::future::{Future, poll_fn};
+use async_std::io::Read;
+use async_std::prelude::*;
+use async_std::stream::Stream;
+use url::Url;
 
-pub fn read_transfer_encoding(headers: &HashMap<String, String>) -> &str {
+pub const SENSITIVE_HEADERS: [&str; 4] = ["Authorization", "Cookie", "Proxy-Authorization", "Set-Cookie"];
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub fn redact_header<'a, H: AsRef<str>>(sensitive: &[H], name: &str, value: &'a str) -> &'a str {
+    match sensitive.iter().any(|header| header.as_ref().eq_ignore_ascii_case(name)) {
+        true => "***",
+        false => value,
+    }
+}
+
+pub fn redact_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    let _ = url.set_username(""); // fails only for URLs that cannot carry credentials
+    let _ = url.set_password(None);
+    url
+}
+
+pub fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => output.push(ALPHABET[(triple >> (18 - 6 * index) & 0x3f) as usize] as char),
+                false => output.push('='),
+            }
+        }
+    }
+    output
+}
+
+pub fn websocket_accept(key: &str) -> String {
+    encode_base64(&sha1(format!("{}{}", key.trim(), WEBSOCKET_GUID).as_bytes()))
+}
+
+pub fn check_websocket_accept(headers: &HashMap<String, String>, key: &str) -> Result<(), Error> {
+    let accept = headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Sec-WebSocket-Accept"))
+        .map(|(_, value)| value.trim());
+
+    match accept {
+        Some(accept) if accept == websocket_accept(key) => Ok(()),
+        Some(_) => Err(Error::new(ErrorKind::InvalidData, "The header `Sec-WebSocket-Accept` does not match the sent key.")),
+        None => Err(Error::new(ErrorKind::InvalidData, "The header `Sec-WebSocket-Accept` cannot found.")),
+    }
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for index in 16..80 {
+            words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+        state[4] = state[4].wrapping_add(e);
+    }
+
+    let mut output = [0u8; 20];
+    for (index, word) in state.iter().enumerate() {
+        output[index * 4..index * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    output
+}
+
+pub fn split_pem(data: &[u8]) -> Result<Vec<String>, Error> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let mut text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return Err(Error::new(ErrorKind::InvalidData, "The CA bundle is not a PEM file.")),
+    };
+    let mut blocks = Vec::new();
+    while let Some(start) = text.find(BEGIN) {
+        let end = match text[start..].find(END) {
+            Some(end) => start + end + END.len(),
+            None => return Err(Error::new(ErrorKind::InvalidData, format!("The CA bundle certificate #{} is not terminated.", blocks.len()))),
+        };
+        blocks.push(text[start..end].to_string());
+        text = &text[end..];
+    }
+
+    match blocks.is_empty() {
+        true => Err(Error::new(ErrorKind::InvalidData, "The CA bundle contains no certificates.")),
+        false => Ok(blocks),
+    }
+}
+
+pub fn env_proxy(scheme: &str, host: &str) -> Option<String> {
+    if let Some(no_proxy) = env_var("no_proxy") {
+        if is_no_proxy(&no_proxy, host) {
+            return None;
+        }
+    }
+    match scheme {
+        "http" => env_var("http_proxy"),
+        "https" => env_var("https_proxy"),
+        _ => None,
+    }.or_else(|| env_var("all_proxy"))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    let value = std::env::var(name).or_else(|_| std::env::var(name.to_ascii_uppercase())).ok()?;
+    match value.trim().is_empty() {
+        true => None,
+        false => Some(value.trim().to_string()),
+    }
+}
+
+pub fn is_no_proxy(no_proxy: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    no_proxy.split(',').map(|entry| entry.trim().to_ascii_lowercase()).any(|entry| {
+        let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+        entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+    })
+}
+
+pub fn read_transfer_codings(headers: &HashMap<String, String>) -> Vec<String> {
     match headers.get("Transfer-Encoding") {
-        Some(encoding) => encoding,
-        None => "identity",
+        Some(encoding) => encoding.split(',')
+            .map(|coding| coding.trim().to_ascii_lowercase())
+            .filter(|coding| !coding.is_empty())
+            .collect(),
+        None => vec!["identity".to_string()],
     }
 }
 
+pub fn is_chunked(headers: &HashMap<String, String>) -> bool {
+    read_transfer_codings(headers).last().map_or(false, |coding| coding == "chunked") // chunked must be the final coding
+}
+
 pub fn read_content_length(headers: &HashMap<String, String>, limit: Option<usize>) -> Result<usize, Error> {
     match headers.get("Content-Length") {
         Some(length) => match length.parse::<usize>() {
             Ok(length) => match limit {
                 Some(limit) => match limit >= length {
                     true => Ok(length),
-                    false => Err(Error::new(ErrorKind::InvalidData, "The operation hit the limit of {} bytes while reading the HTTP body chunk data.")),
+                    false => Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP body data.", limit))),
                 },
                 None => Ok(length),
             },
@@ -23,3 +191,402 @@ pub fn read_content_length(headers: &HashMap<String, String>, limit: Option<usiz
         None => Err(Error::new(ErrorKind::InvalidData, "The header `Content-Length` cannot found.")),
     }
 }
+
+pub fn check_content_length(headers: &HashMap<String, String>, value: &str) -> Result<String, Error> {
+    let mut lengths = value.split(',').map(|length| length.trim());
+    let first = lengths.next().unwrap_or("");
+    let previous = headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .map(|(_, value)| value.trim());
+
+    match lengths.all(|length| length == first) && previous.map_or(true, |previous| previous == first) {
+        true => Ok(first.to_string()),
+        false => Err(Error::new(ErrorKind::InvalidData, "The response contains conflicting `Content-Length` headers.")),
+    }
+}
+
+pub fn check_framing(headers: &HashMap<String, String>) -> Result<(), Error> {
+    let has_length = headers.keys().any(|name| name.eq_ignore_ascii_case("Content-Length"));
+    let has_encoding = headers.keys().any(|name| name.eq_ignore_ascii_case("Transfer-Encoding"));
+
+    match has_length && has_encoding {
+        true => Err(Error::new(ErrorKind::InvalidData, "The response contains both `Content-Length` and `Transfer-Encoding` headers.")),
+        false => Ok(()),
+    }
+}
+
+pub async fn read_sized<R>(reader: &mut R, data: &mut Vec<u8>, length: usize) -> Result<usize, Error>
+    where
+    R: Read + Unpin,
+{
+    let mut buffer = [0u8; 8192];
+    let mut total = 0;
+
+    while total < length {
+        let size = reader.read(&mut buffer[..(length - total).min(8192)]).await?;
+        if size == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, format!("The response body is incomplete, {} of {} bytes are missing.", length - total, length)));
+        }
+
+        total += size;
+        data.extend_from_slice(&buffer[..size]);
+    }
+    Ok(total)
+}
+
+pub async fn read_to_eof<R>(reader: &mut R, data: &mut Vec<u8>, limit: Option<usize>) -> Result<usize, Error>
+    where
+    R: Read + Unpin,
+{
+    let mut buffer = [0u8; 8192];
+    let mut total = 0;
+
+    loop {
+        let size = reader.read(&mut buffer).await?;
+        if size == 0 {
+            return Ok(total);
+        }
+
+        total += size;
+        if let Some(limit) = limit {
+            if total > limit {
+                return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the HTTP body data.", limit)));
+            }
+        }
+        data.extend_from_slice(&buffer[..size]);
+    }
+}
+
+pub fn escape_bytes(bytes: &[u8], limit: usize) -> String {
+    let mut output: String = bytes.iter()
+        .take(limit)
+        .flat_map(|byte| std::ascii::escape_default(*byte))
+        .map(char::from)
+        .collect();
+    if bytes.len() > limit {
+        output.push_str("...");
+    }
+    output
+}
+
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.trim().split_whitespace().collect();
+    if parts.len() != 6 || parts[5] != "GMT" { // IMF-fixdate
+        return None;
+    }
+
+    let day = parts[1].parse::<u64>().ok()?;
+    let month = match parts[2] {
+        "Jan" => 1, "Feb" => 2, "Mar" => 3, "Apr" => 4, "May" => 5, "Jun" => 6,
+        "Jul" => 7, "Aug" => 8, "Sep" => 9, "Oct" => 10, "Nov" => 11, "Dec" => 12,
+        _ => return None,
+    };
+    let year = parts[3].parse::<u64>().ok()?;
+    let time: Vec<u64> = parts[4].split(':').filter_map(|part| part.parse::<u64>().ok()).collect();
+    if time.len() != 3 || day == 0 || day > 31 || year < 1970 || time[0] > 23 || time[1] > 59 || time[2] > 60 {
+        return None;
+    }
+
+    let days = days_since_epoch(year, month, day);
+    let seconds = days * 86400 + time[0] * 3600 + time[1] * 60 + time[2];
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let decoded = match bytes[index] {
+            b'%' if index + 2 < bytes.len() => match std::str::from_utf8(&bytes[index + 1..index + 3]) {
+                Ok(hex) => u8::from_str_radix(hex, 16).ok(),
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        match decoded {
+            Some(byte) => {
+                output.push(byte);
+                index += 3;
+            },
+            None => {
+                output.push(bytes[index]);
+                index += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&output).to_string()
+}
+
+pub fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                segments.pop();
+            },
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut output = format!("/{}", segments.join("/"));
+    if !segments.is_empty() && (path.ends_with('/') || path.ends_with("/.") || path.ends_with("/..")) {
+        output.push('/');
+    }
+    output
+}
+
+pub fn poll_once<F: Future>(future: F) -> Option<F::Output> {
+    fn noop_raw() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker { noop_raw() }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    let waker = unsafe { Waker::from_raw(noop_raw()) }; // the vtable ignores its data pointer
+    match Box::pin(future).as_mut().poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+pub async fn cancellable<T, F, C>(future: F, cancel: C) -> Result<T, Error>
+    where
+    F: Future<Output = Result<T, Error>>,
+    C: Future<Output = ()>,
+{
+    let mut future = Box::pin(future);
+    let mut cancel = Box::pin(cancel);
+
+    poll_fn(|cx| {
+        if cancel.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err(Error::new(ErrorKind::Interrupted, "The request has been cancelled.")));
+        }
+        future.as_mut().poll(cx)
+    }).await
+}
+
+pub fn interleave_addrs(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let prefer_ipv6 = match addrs.first() {
+        Some(addr) => addr.is_ipv6(),
+        None => return addrs,
+    };
+    let (mut primary, mut secondary): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter()
+        .partition(|addr| addr.is_ipv6() == prefer_ipv6);
+    primary.reverse();
+    secondary.reverse();
+
+    let mut output = Vec::new();
+    while !primary.is_empty() || !secondary.is_empty() {
+        if let Some(addr) = primary.pop() {
+            output.push(addr);
+        }
+        if let Some(addr) = secondary.pop() {
+            output.push(addr);
+        }
+    }
+    output
+}
+
+pub async fn first_ok<T>(attempts: Vec<Pin<Box<dyn Future<Output = Result<T, Error>> + Send>>>, stagger: Duration) -> Result<T, Error> {
+    let mut attempts = attempts.into_iter();
+    let mut running = Vec::new();
+    let mut timer: Option<Pin<Box<dyn Future<Output = ()> + Send>>> = None;
+    let mut last_error = Error::new(ErrorKind::AddrNotAvailable, "The address could not be resolved.");
+    let (mut start_now, mut exhausted) = (true, false);
+
+    poll_fn(|cx| loop {
+        if start_now {
+            start_now = false;
+            match attempts.next() {
+                Some(attempt) => {
+                    running.push(attempt);
+                    timer = Some(Box::pin(async_std::task::sleep(stagger)));
+                },
+                None => {
+                    exhausted = true;
+                    timer = None;
+                },
+            };
+        }
+
+        let mut index = 0;
+        while index < running.len() {
+            match running[index].as_mut().poll(cx) {
+                Poll::Ready(Ok(value)) => return Poll::Ready(Ok(value)),
+                Poll::Ready(Err(e)) => {
+                    running.remove(index);
+                    last_error = e;
+                    start_now = !exhausted; // a failure starts the next attempt without waiting (RFC 8305)
+                },
+                Poll::Pending => index += 1,
+            }
+        }
+
+        if start_now {
+            continue;
+        } else if running.is_empty() && exhausted {
+            return Poll::Ready(Err(Error::new(last_error.kind(), last_error.to_string())));
+        }
+        match timer.as_mut() {
+            Some(timer) if timer.as_mut().poll(cx).is_ready() => start_now = true,
+            _ => return Poll::Pending,
+        };
+    }).await
+}
+
+pub fn guess_mime_type<P: AsRef<Path>>(path: P) -> Option<&'static str> {
+    let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match extension.as_str() {
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    let mime = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        "image/gif"
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        "image/webp"
+    } else if data.starts_with(b"%PDF-") {
+        "application/pdf"
+    } else if data.starts_with(b"PK\x03\x04") {
+        "application/zip"
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+        "application/gzip"
+    } else if data.starts_with(b"\0asm") {
+        "application/wasm"
+    } else if data.starts_with(b"<?xml") {
+        "application/xml"
+    } else {
+        return None;
+    };
+    Some(mime)
+}
+
+pub type Step<'a, S, T> = Pin<Box<dyn Future<Output = Option<(T, S)>> + Send + 'a>>;
+
+pub struct Unfold<'a, S, T> {
+    state: Option<S>,
+    next: fn(S) -> Step<'a, S, T>,
+    pending: Option<Step<'a, S, T>>,
+}
+
+pub fn unfold<'a, S, T>(state: S, next: fn(S) -> Step<'a, S, T>) -> Unfold<'a, S, T> {
+    Unfold {
+        state: Some(state),
+        next,
+        pending: None,
+    }
+}
+
+impl<S: Unpin, T> Stream for Unfold<'_, S, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let this = &mut *self;
+        if this.pending.is_none() {
+            match this.state.take() {
+                Some(state) => this.pending = Some((this.next)(state)),
+                None => return Poll::Ready(None),
+            };
+        }
+
+        let step = match this.pending.as_mut() {
+            Some(pending) => pending.as_mut().poll(cx),
+            None => return Poll::Ready(None),
+        };
+        match step {
+            Poll::Ready(Some((item, state))) => {
+                this.pending = None;
+                this.state = Some(state);
+                Poll::Ready(Some(item))
+            },
+            Poll::Ready(None) => {
+                this.pending = None;
+                Poll::Ready(None)
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+pub struct StreamReader<S> {
+    stream: S,
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+impl<S> StreamReader<S> {
+
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+            offset: 0,
+        }
+    }
+}
+
+impl<S> Read for StreamReader<S>
+    where
+    S: Stream<Item = Result<Vec<u8>, Error>> + Unpin,
+{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize, Error>> {
+        let this = &mut *self;
+        loop {
+            if this.offset < this.buffer.len() { // drain the current item first
+                let size = buf.len().min(this.buffer.len() - this.offset);
+                buf[..size].copy_from_slice(&this.buffer[this.offset..this.offset + size]);
+                this.offset += size;
+                return Poll::Ready(Ok(size));
+            }
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(data))) => {
+                    this.buffer = data;
+                    this.offset = 0;
+                },
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            };
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Error, ErrorKind};
+use std::time::SystemTime;
+use async_std::prelude::*;
+use crate::{Duplex, encode_base64};
+
+const MESSAGE_LIMIT: usize = 16 * 1024 * 1024;
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+pub struct WebSocketStream<'a> {
+    stream: Box<dyn Duplex + 'a>,
+    message_limit: usize,
+    fragments: Option<(u8, Vec<u8>)>,
+    closing: bool,
+    closed: bool,
+}
+
+impl<'a> WebSocketStream<'a> {
+
+    pub fn new(stream: Box<dyn Duplex + 'a>) -> Self {
+        Self {
+            stream,
+            message_limit: MESSAGE_LIMIT,
+            fragments: None,
+            closing: false,
+            closed: false,
+        }
+    }
+
+    pub fn message_limit(&self) -> usize {
+        self.message_limit
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn set_message_limit(&mut self, value: usize) {
+        self.message_limit = value;
+    }
+
+    pub async fn send(&mut self, message: Message) -> Result<(), Error> {
+        if self.closing {
+            return Err(Error::new(ErrorKind::NotConnected, "The WebSocket connection is closed."));
+        }
+        match message {
+            Message::Text(data) => self.write_frame(TEXT, data.as_bytes()).await,
+            Message::Binary(data) => self.write_frame(BINARY, &data).await,
+            Message::Ping(data) => self.write_frame(PING, &data).await,
+            Message::Pong(data) => self.write_frame(PONG, &data).await,
+            Message::Close(reason) => {
+                self.closing = true;
+                self.write_frame(CLOSE, &close_payload(reason)).await
+            },
+        }
+    }
+
+    pub async fn recv(&mut self) -> Result<Message, Error> {
+        if self.closed {
+            return Err(Error::new(ErrorKind::NotConnected, "The WebSocket connection is closed."));
+        }
+
+        loop {
+            let (fin, opcode, data) = self.read_frame().await?;
+            match opcode {
+                PING => {
+                    if !self.closing {
+                        self.write_frame(PONG, &data).await?; // answered before handing it over
+                    }
+                    return Ok(Message::Ping(data));
+                },
+                PONG => return Ok(Message::Pong(data)),
+                CLOSE => {
+                    let reason = parse_close(&data)?;
+                    if !self.closing {
+                        self.closing = true;
+                        self.write_frame(CLOSE, &data[..data.len().min(2)]).await?;
+                    }
+                    self.closed = true;
+                    return Ok(Message::Close(reason));
+                },
+                TEXT | BINARY if self.fragments.is_none() => self.fragments = Some((opcode, data)),
+                CONTINUATION => match &mut self.fragments {
+                    Some((_, buffer)) if buffer.len() + data.len() > self.message_limit => {
+                        return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the WebSocket message.", self.message_limit)));
+                    },
+                    Some((_, buffer)) => buffer.extend_from_slice(&data),
+                    None => return Err(Error::new(ErrorKind::InvalidData, "The WebSocket continuation frame is unexpected.")),
+                },
+                opcode => return Err(Error::new(ErrorKind::InvalidData, format!("The WebSocket opcode `{}` is unexpected.", opcode))),
+            };
+
+            if fin {
+                return match self.fragments.take() {
+                    Some((TEXT, data)) => match String::from_utf8(data) {
+                        Ok(text) => Ok(Message::Text(text)),
+                        Err(_) => Err(Error::new(ErrorKind::InvalidData, "The WebSocket text message is not valid UTF-8.")),
+                    },
+                    Some((_, data)) => Ok(Message::Binary(data)),
+                    None => Err(Error::new(ErrorKind::InvalidData, "The WebSocket message is incomplete.")),
+                };
+            }
+        }
+    }
+
+    pub async fn close(&mut self, code: u16, reason: &str) -> Result<(), Error> {
+        self.send(Message::Close(Some((code, reason.to_string())))).await?;
+        while !self.closed {
+            if let Err(e) = self.recv().await {
+                return match e.kind() {
+                    ErrorKind::UnexpectedEof => Ok(()), // peer dropped without answering
+                    _ => Err(e),
+                };
+            }
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> Box<dyn Duplex + 'a> {
+        self.stream
+    }
+
+    async fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), Error> {
+        let mut head = [0u8; 2];
+        self.stream.read_exact(&mut head).await?;
+        let fin = head[0] & 0x80 != 0;
+        let opcode = head[0] & 0x0f;
+        if head[0] & 0x70 != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "The WebSocket frame uses an unnegotiated extension."));
+        } else if head[1] & 0x80 != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "The WebSocket frame from the server must not be masked."));
+        } else if opcode & 0x8 != 0 && !fin {
+            return Err(Error::new(ErrorKind::InvalidData, "The WebSocket control frame must not be fragmented."));
+        } else if opcode & 0x8 != 0 && head[1] & 0x7f > 125 {
+            return Err(Error::new(ErrorKind::InvalidData, "The WebSocket control frame payload must not exceed 125 bytes."));
+        }
+
+        let length = match head[1] & 0x7f {
+            126 => {
+                let mut bytes = [0u8; 2];
+                self.stream.read_exact(&mut bytes).await?;
+                u16::from_be_bytes(bytes) as u64
+            },
+            127 => {
+                let mut bytes = [0u8; 8];
+                self.stream.read_exact(&mut bytes).await?;
+                u64::from_be_bytes(bytes)
+            },
+            length => length as u64,
+        };
+        if length > self.message_limit as u64 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The operation hit the limit of {} bytes while reading the WebSocket message.", self.message_limit)));
+        }
+
+        let mut data = vec![0u8; length as usize];
+        self.stream.read_exact(&mut data).await?;
+        Ok((fin, opcode, data))
+    }
+
+    async fn write_frame(&mut self, opcode: u8, data: &[u8]) -> Result<(), Error> {
+        let mut frame = vec![0x80 | opcode];
+        match data.len() {
+            length if length < 126 => frame.push(0x80 | length as u8),
+            length if length <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            },
+            length => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            },
+        };
+
+        let mask = random_u64().to_be_bytes();
+        let mask = [mask[0], mask[1], mask[2], mask[3]];
+        frame.extend_from_slice(&mask);
+        let offset = frame.len();
+        frame.extend_from_slice(data);
+        apply_mask(&mut frame[offset..], mask); // clients always mask
+
+        self.stream.write_all(&frame).await?;
+        self.stream.flush().await
+    }
+}
+
+pub(crate) fn websocket_key() -> String {
+    let mut key = random_u64().to_be_bytes().to_vec();
+    key.extend_from_slice(&random_u64().to_be_bytes());
+    encode_base64(&key)
+}
+
+fn close_payload(reason: Option<(u16, String)>) -> Vec<u8> {
+    match reason {
+        Some((code, reason)) => {
+            let mut data = code.to_be_bytes().to_vec();
+            data.extend_from_slice(reason.as_bytes());
+            data
+        },
+        None => Vec::new(),
+    }
+}
+
+fn parse_close(data: &[u8]) -> Result<Option<(u16, String)>, Error> {
+    match data.len() {
+        0 => Ok(None),
+        1 => Err(Error::new(ErrorKind::InvalidData, "The WebSocket close frame is invalid.")),
+        _ => match String::from_utf8(data[2..].to_vec()) {
+            Ok(reason) => Ok(Some((u16::from_be_bytes([data[0], data[1]]), reason))),
+            Err(_) => Err(Error::new(ErrorKind::InvalidData, "The WebSocket close frame is invalid.")),
+        },
+    }
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    for (index, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+}
+
+fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher(); // randomly seeded, good enough for keys and masks
+    if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    hasher.finish()
+}